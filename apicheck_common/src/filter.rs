@@ -0,0 +1,160 @@
+//! Selection of the items of the API by logical path and by kind.
+//!
+//! Paths are matched against glob patterns like `crate::ffi::**`, where `*` matches any
+//! part of a name and `**` matches any number of path segments. Kinds are the item types of
+//! the JSON description (`function`, `trait`, `impl`, ...).
+
+use json::JsonValue;
+
+/// Include and exclude filters on item paths and kinds.
+///
+/// An item is kept if it matches one of the include patterns (or if there are none) and
+/// none of the exclude patterns. Excluding a module also excludes its items and submodules,
+/// including those defined in other files. Modules are kept if they contain kept items.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ItemFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    include_kinds: Vec<String>,
+    exclude_kinds: Vec<String>,
+}
+
+impl ItemFilter {
+    pub fn new() -> ItemFilter {
+        ItemFilter::default()
+    }
+
+    pub fn include(&mut self, pattern: &str) {
+        self.include.push(pattern.to_owned());
+    }
+
+    pub fn exclude(&mut self, pattern: &str) {
+        self.exclude.push(pattern.to_owned());
+    }
+
+    /// Keep only the items of this kind (plural forms like `functions` are accepted).
+    pub fn include_kind(&mut self, kind: &str) {
+        self.include_kinds.push(normalize_kind(kind));
+    }
+
+    pub fn exclude_kind(&mut self, kind: &str) {
+        self.exclude_kinds.push(normalize_kind(kind));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.include_kinds.is_empty()
+            && self.exclude_kinds.is_empty()
+    }
+
+    /// Remove the items which are not selected from an API description.
+    pub fn apply(&self, json: &mut JsonValue) {
+        if self.is_empty() {
+            return;
+        }
+        let modules: Vec<JsonValue> = json["modules"]
+            .members()
+            .filter_map(|module| {
+                let path = module["module"].as_str().unwrap_or("crate").to_owned();
+                self.filter_module(module.clone(), &path)
+            })
+            .collect();
+        json["modules"] = JsonValue::Array(modules);
+    }
+
+    /// Filter the items of a module, and return the module if it is still selected.
+    fn filter_module(&self, mut module: JsonValue, path: &str) -> Option<JsonValue> {
+        if self.is_excluded(path) {
+            return None;
+        }
+        let items: Vec<JsonValue> = module["items"]
+            .members()
+            .filter_map(|item| {
+                let item_path = match item_name(item) {
+                    Some(name) => format!("{}::{}", path, name),
+                    None => path.to_owned(),
+                };
+                if item["type"] == "mod" {
+                    self.filter_module(item.clone(), &item_path)
+                } else if self.is_selected(&item_path, item["type"].as_str().unwrap_or("")) {
+                    Some(item.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if items.is_empty() && !self.is_selected(path, "mod") {
+            return None;
+        }
+        module["items"] = JsonValue::Array(items);
+        Some(module)
+    }
+
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|p| path_matches(p, path) || ancestors(path).any(|a| path_matches(p, a)))
+    }
+
+    fn is_selected(&self, path: &str, kind: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| path_matches(p, path)))
+            && !self.is_excluded(path)
+            && (self.include_kinds.is_empty() || self.include_kinds.iter().any(|k| k == kind))
+            && !self.exclude_kinds.iter().any(|k| k == kind)
+    }
+}
+
+/// Returns the name used in the path of an item: its name, or the implemented type for an
+/// `impl` block.
+fn item_name(item: &JsonValue) -> Option<&str> {
+    match item["name"].as_str() {
+        Some(name) if !name.is_empty() => Some(name),
+        _ => item["impl_type"]
+            .as_str()
+            .and_then(|ty| ty.split('<').next())
+            .filter(|ty| !ty.is_empty()),
+    }
+}
+
+/// Returns the paths of the modules containing `path`, like `crate::a` and `crate` for
+/// `crate::a::b`.
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices("::").map(move |(idx, _)| &path[..idx])
+}
+
+fn normalize_kind(kind: &str) -> String {
+    kind.trim().trim_end_matches('s').to_owned()
+}
+
+/// Returns `true` if the logical path `path` (like `crate::a::b`) matches `pattern`.
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split("::").collect();
+    let path: Vec<&str> = path.split("::").collect();
+    segments_match(&pattern, &path)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| segments_match(rest, &path[i..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path_rest)) => name_matches(first, name) && segments_match(rest, path_rest),
+            None => false,
+        },
+    }
+}
+
+/// Match a name with a pattern where `*` matches any sequence of characters.
+fn name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(idx) => {
+            let (prefix, rest) = (&pattern[..idx], &pattern[idx + 1..]);
+            name.starts_with(prefix)
+                && (prefix.len()..=name.len())
+                    .filter(|&i| name.is_char_boundary(i))
+                    .any(|i| name_matches(rest, &name[i..]))
+        }
+    }
+}
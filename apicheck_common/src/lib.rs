@@ -0,0 +1,7 @@
+//! Processing of the JSON API descriptions shared by `apicheck` and `apidiff`, so that both
+//! apply the same rules.
+
+extern crate json;
+
+pub mod filter;
+pub mod unsafety;
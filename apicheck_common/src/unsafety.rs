@@ -0,0 +1,135 @@
+//! Unsafe surface: `unsafe fn`, `unsafe trait` and `unsafe impl` items, `static mut` items,
+//! and raw pointers in public fields and signatures.
+
+use std::fmt;
+
+use json::JsonValue;
+
+/// A part of an item which exposes `unsafe` to the users of the crate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnsafePart {
+    /// `unsafe fn`
+    Function,
+    /// `unsafe trait`
+    Trait,
+    /// `unsafe impl`
+    Impl,
+    /// `static mut`
+    StaticMut,
+    /// Argument of a function with a raw pointer type
+    Argument { name: String, ty: String },
+    /// Raw pointer return type of a function
+    Output(String),
+    /// Raw pointer type of a static or a const
+    Type(String),
+    /// Public field of a struct or union, or field of an enum variant, with a raw pointer
+    /// type
+    Field {
+        variant: Option<String>,
+        index: usize,
+        name: String,
+        ty: String,
+    },
+}
+
+impl fmt::Display for UnsafePart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnsafePart::Function => write!(f, "unsafe fn"),
+            UnsafePart::Trait => write!(f, "unsafe trait"),
+            UnsafePart::Impl => write!(f, "unsafe impl"),
+            UnsafePart::StaticMut => write!(f, "static mut"),
+            UnsafePart::Argument { ref name, ref ty } => {
+                write!(f, "raw pointer argument '{}: {}'", name, ty)
+            }
+            UnsafePart::Output(ref ty) => write!(f, "raw pointer return type '{}'", ty),
+            UnsafePart::Type(ref ty) => write!(f, "raw pointer type '{}'", ty),
+            UnsafePart::Field {
+                variant: Some(ref variant),
+                ref name,
+                ref ty,
+                ..
+            } => write!(f, "raw pointer field '{}.{}: {}'", variant, name, ty),
+            UnsafePart::Field {
+                variant: None,
+                ref name,
+                ref ty,
+                ..
+            } => write!(f, "raw pointer field '{}: {}'", name, ty),
+        }
+    }
+}
+
+/// Returns the unsafe parts of an item, without the items it contains.
+pub fn unsafe_parts(json: &JsonValue) -> Vec<UnsafePart> {
+    let mut parts = Vec::new();
+    match json["type"].as_str() {
+        Some("function") | Some("method") => {
+            if json["unsafe"] == true {
+                parts.push(UnsafePart::Function);
+            }
+            for input in json["inputs"].members() {
+                if has_raw_pointer_type(input) {
+                    parts.push(UnsafePart::Argument {
+                        name: input["name"].to_string(),
+                        ty: input["type"].to_string(),
+                    });
+                }
+            }
+            if let Some(output) = json["output"].as_str().filter(|ty| is_raw_pointer(ty)) {
+                parts.push(UnsafePart::Output(output.to_owned()));
+            }
+        }
+        Some("trait") if json["unsafe"] == true => parts.push(UnsafePart::Trait),
+        Some("impl") if json["unsafe"] == true => parts.push(UnsafePart::Impl),
+        Some("static") | Some("const") => {
+            if json["mutability"] == "mut" {
+                parts.push(UnsafePart::StaticMut);
+            }
+            if let Some(ty) = json["subtype"].as_str().filter(|ty| is_raw_pointer(ty)) {
+                parts.push(UnsafePart::Type(ty.to_owned()));
+            }
+        }
+        Some("struct") | Some("union") => {
+            for (index, field) in json["fields"].members().enumerate() {
+                if field["visibility"] == "public" && has_raw_pointer_type(field) {
+                    parts.push(field_part(None, index, field));
+                }
+            }
+        }
+        Some("enum") => {
+            // the fields of the variants are public
+            for variant in json["fields"].members() {
+                for (index, field) in variant["fields"].members().enumerate() {
+                    if has_raw_pointer_type(field) {
+                        parts.push(field_part(Some(variant["name"].to_string()), index, field));
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+    parts
+}
+
+fn field_part(variant: Option<String>, index: usize, field: &JsonValue) -> UnsafePart {
+    let name = match field["name"].as_str() {
+        Some(name) if name != "<anon>" => name.to_owned(),
+        _ => index.to_string(),
+    };
+    UnsafePart::Field {
+        variant,
+        index,
+        name,
+        ty: field["type"].to_string(),
+    }
+}
+
+fn has_raw_pointer_type(json: &JsonValue) -> bool {
+    json["type"].as_str().is_some_and(is_raw_pointer)
+}
+
+/// Returns `true` if a type contains a raw pointer, like `*const u8` or `Option<*mut T>`.
+fn is_raw_pointer(ty: &str) -> bool {
+    ty.contains("*const ") || ty.contains("*mut ")
+}
@@ -0,0 +1,199 @@
+//! ABI changes: layout changes of `#[repr]` types, and signature changes of functions
+//! exported with the C ABI.
+//!
+//! These changes can break C consumers even when they are source-compatible, for example
+//! when the named fields of a `#[repr(C)]` struct are reordered.
+
+use json::JsonValue;
+
+/// Returns a description of each ABI change between two versions of an item.
+pub fn abi_changes(json1: &JsonValue, json2: &JsonValue) -> Vec<String> {
+    match json1["type"].as_str() {
+        Some("struct") | Some("union") | Some("enum") if has_repr(json1) || has_repr(json2) => {
+            layout_changes(json1, json2)
+        }
+        Some("function") if is_exported(json1) || is_exported(json2) => {
+            signature_changes(json1, json2)
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn has_repr(json: &JsonValue) -> bool {
+    !json["repr"].is_empty()
+}
+
+/// Returns `true` for functions with an explicit ABI, or exported with `#[no_mangle]` or
+/// `#[export_name]`.
+fn is_exported(json: &JsonValue) -> bool {
+    let abi = json["extern"].as_str().unwrap_or("");
+    (!abi.is_empty() && abi != "Rust") || !symbol_attrs(json).is_empty()
+}
+
+/// Returns the attributes which set the symbol name of a function.
+fn symbol_attrs(json: &JsonValue) -> Vec<&str> {
+    json["attrs"]
+        .members()
+        .filter_map(|attr| attr.as_str())
+        .filter(|attr| attr.starts_with("#[no_mangle") || attr.starts_with("#[export_name"))
+        .collect()
+}
+
+fn repr_string(json: &JsonValue) -> String {
+    let hints: Vec<&str> = json["repr"].members().filter_map(|r| r.as_str()).collect();
+    format!("#[repr({})]", hints.join(", "))
+}
+
+/// Compare the layout of two types: representation, fields (or variants) and their order.
+fn layout_changes(json1: &JsonValue, json2: &JsonValue) -> Vec<String> {
+    let mut changes = Vec::new();
+    if json1["repr"] != json2["repr"] {
+        changes.push(format!(
+            "representation changed from '{}' to '{}'",
+            repr_string(json1),
+            repr_string(json2)
+        ));
+    }
+    let what = if json1["type"] == "enum" {
+        "variant"
+    } else {
+        "field"
+    };
+    let fields1 = fields(json1);
+    let fields2 = fields(json2);
+    for f1 in &fields1 {
+        match fields2.iter().find(|f2| f2.key == f1.key) {
+            None => changes.push(format!("{} '{}' removed", what, f1.key)),
+            Some(f2) => {
+                if f1.ty != f2.ty {
+                    changes.push(format!(
+                        "{} '{}' changed from '{}' to '{}'",
+                        what, f1.key, f1.ty, f2.ty
+                    ));
+                }
+                if f1.discriminant != f2.discriminant {
+                    changes.push(format!(
+                        "discriminant of '{}' changed from '{}' to '{}'",
+                        f1.key,
+                        f1.discriminant.unwrap_or("<implicit>"),
+                        f2.discriminant.unwrap_or("<implicit>")
+                    ));
+                }
+            }
+        }
+    }
+    for f2 in &fields2 {
+        if !fields1.iter().any(|f1| f1.key == f2.key) {
+            changes.push(format!("{} '{}' added", what, f2.key));
+        }
+    }
+    // order of the fields present in both versions
+    let order1: Vec<&str> = fields1
+        .iter()
+        .map(|f| f.key.as_str())
+        .filter(|k| fields2.iter().any(|f| f.key == *k))
+        .collect();
+    let order2: Vec<&str> = fields2
+        .iter()
+        .map(|f| f.key.as_str())
+        .filter(|k| fields1.iter().any(|f| f.key == *k))
+        .collect();
+    if order1 != order2 {
+        changes.push(format!(
+            "{}s reordered from ({}) to ({})",
+            what,
+            order1.join(", "),
+            order2.join(", ")
+        ));
+    }
+    changes
+}
+
+struct Field<'a> {
+    /// Name, or position for tuple fields
+    key: String,
+    ty: String,
+    discriminant: Option<&'a str>,
+}
+
+fn fields<'a>(json: &'a JsonValue) -> Vec<Field<'a>> {
+    json["fields"]
+        .members()
+        .enumerate()
+        .map(|(idx, f)| {
+            let key = match f["name"].as_str() {
+                Some(name) if name != "<anon>" => name.to_owned(),
+                _ => idx.to_string(),
+            };
+            Field {
+                key,
+                ty: field_type(f),
+                discriminant: f["discriminant"].as_str(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the type of a field, or the types of the fields of an enum variant.
+fn field_type(json: &JsonValue) -> String {
+    if let Some(ty) = json["type"].as_str() {
+        return ty.to_owned();
+    }
+    let fields: Vec<String> = json["fields"]
+        .members()
+        .map(|f| match f["name"].as_str() {
+            Some(name) if name != "<anon>" => format!("{}: {}", name, field_type(f)),
+            _ => field_type(f),
+        })
+        .collect();
+    format!("({})", fields.join(", "))
+}
+
+/// Compare the signatures of two exported functions.
+fn signature_changes(json1: &JsonValue, json2: &JsonValue) -> Vec<String> {
+    let mut changes = Vec::new();
+    if json1["extern"] != json2["extern"] {
+        changes.push(format!(
+            "calling convention changed from '{}' to '{}'",
+            json1["extern"], json2["extern"]
+        ));
+    }
+    let symbol1 = symbol_attrs(json1);
+    let symbol2 = symbol_attrs(json2);
+    if symbol1 != symbol2 {
+        changes.push(format!(
+            "symbol attributes changed from '{}' to '{}'",
+            symbol1.join(" "),
+            symbol2.join(" ")
+        ));
+    }
+    let inputs1 = input_types(json1);
+    let inputs2 = input_types(json2);
+    if inputs1 != inputs2 {
+        changes.push(format!(
+            "argument types changed from ({}) to ({})",
+            inputs1.join(", "),
+            inputs2.join(", ")
+        ));
+    }
+    if json1["output"] != json2["output"] {
+        changes.push(format!(
+            "return type changed from '{}' to '{}'",
+            json1["output"], json2["output"]
+        ));
+    }
+    if json1["variadic"] != json2["variadic"] {
+        changes.push(format!(
+            "variadic changed from {} to {}",
+            json1["variadic"], json2["variadic"]
+        ));
+    }
+    changes
+}
+
+fn input_types(json: &JsonValue) -> Vec<&str> {
+    json["inputs"]
+        .members()
+        .filter_map(|i| i["type"].as_str())
+        .collect()
+}
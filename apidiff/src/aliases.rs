@@ -0,0 +1,479 @@
+//! Expansion of the type aliases declared in the crate.
+//!
+//! Replacing `Result<T, MyError>` by `Result<T>`, where the crate declares
+//! `pub type Result<T> = std::result::Result<T, MyError>`, does not change the API. Types are
+//! compared with the aliases of each version expanded, so that only changes of the expanded
+//! types are reported.
+
+use json::JsonValue;
+use std::collections::HashMap;
+
+/// Limit for aliases declared with other aliases (and for recursive declarations).
+const MAX_DEPTH: usize = 16;
+
+/// A type alias declared in the crate.
+struct Alias {
+    /// Path of the module declaring the alias, like `crate::error`
+    module: String,
+    /// Lifetime and type parameters, with their default value
+    params: Vec<(String, Option<String>)>,
+    subtype: String,
+}
+
+struct Aliases {
+    /// Aliases by path, like `crate::error::Result`
+    aliases: HashMap<String, Alias>,
+    /// Name of the crate, which can be used in paths with `extern crate self as name`
+    crate_name: Option<String>,
+    /// Names imported from the crate by each module, like `Result` for
+    /// `use crate::error::Result`
+    imports: HashMap<String, HashMap<String, String>>,
+    /// Paths are relative to the crate root
+    edition_2015: bool,
+}
+
+/// Expand the type aliases of a description, or of each crate of a workspace description.
+///
+/// The expanded types are stored in the `resolved_` keys, like the canonical types
+/// extracted with `--resolve-paths`. `crate_name` overrides the name of the crate recorded in
+/// the description.
+pub fn expand_aliases(json: &mut JsonValue, crate_name: Option<&str>) {
+    if json.has_key("crates") {
+        for (name, krate) in json["crates"].entries_mut() {
+            expand_crate_aliases(krate, Some(name.replace('-', "_")));
+        }
+    } else {
+        let crate_name = crate_name
+            .or_else(|| json["crate"]["name"].as_str())
+            .map(|name| name.replace('-', "_"));
+        expand_crate_aliases(json, crate_name);
+    }
+}
+
+fn expand_crate_aliases(json: &mut JsonValue, crate_name: Option<String>) {
+    let mut aliases = Aliases {
+        aliases: HashMap::new(),
+        crate_name,
+        imports: HashMap::new(),
+        edition_2015: json["edition"] == "2015",
+    };
+    for module in json["modules"].members() {
+        let path = module["module"].as_str().unwrap_or("crate");
+        aliases.collect(module, path);
+    }
+    if aliases.aliases.is_empty() {
+        return;
+    }
+    for module in json["modules"].members_mut() {
+        let path = module["module"].as_str().unwrap_or("crate").to_owned();
+        expand_module(module, &path, &aliases);
+    }
+}
+
+impl Aliases {
+    fn collect(&mut self, module: &JsonValue, path: &str) {
+        let imports: HashMap<String, String> = module["imports"]
+            .entries()
+            .filter_map(|(name, import)| Some((name.to_owned(), import.as_str()?.to_owned())))
+            .collect();
+        if !imports.is_empty() {
+            self.imports.insert(path.to_owned(), imports);
+        }
+        for item in module["items"].members() {
+            match item["type"].as_str() {
+                Some("mod") => self.collect(item, &format!("{}::{}", path, item["name"])),
+                Some("type") => {
+                    let subtype = match value(item, "subtype") {
+                        Some(subtype) => subtype.to_owned(),
+                        // associated type declared in a trait
+                        None => continue,
+                    };
+                    let alias = Alias {
+                        module: path.to_owned(),
+                        params: generic_params(item["generics"].as_str().unwrap_or("")),
+                        subtype,
+                    };
+                    self.aliases
+                        .insert(format!("{}::{}", path, item["name"]), alias);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Returns the alias used with `path` in `module`, if any.
+    fn lookup(&self, path: &str, module: &str) -> Option<&Alias> {
+        let first = path.split("::").next().unwrap_or("");
+        // a name imported with `use` cannot also be declared in the module
+        let import = self
+            .imports
+            .get(module)
+            .and_then(|imports| imports.get(first));
+        match import {
+            Some(import) => {
+                let path = format!("{}{}", import, &path[first.len()..]);
+                self.lookup_path(&path, module, true)
+            }
+            None => self.lookup_path(path, module, false),
+        }
+    }
+
+    /// Returns the alias of `path` in `module`, `imported` being `true` for the paths of `use`
+    /// declarations.
+    fn lookup_path(&self, path: &str, module: &str, imported: bool) -> Option<&Alias> {
+        let first = path.split("::").next().unwrap_or("");
+        if first == "crate" || first == "self" || first == "super" {
+            return self.aliases.get(&relative_path(path, module));
+        }
+        if path.contains("::") && Some(first) == self.crate_name.as_deref() {
+            return self.aliases.get(&format!("crate{}", &path[first.len()..]));
+        }
+        // paths are relative to the crate root in the 2015 edition, for `use` declarations and
+        // for paths with several segments
+        if self.edition_2015 && path.starts_with("::") {
+            return self.aliases.get(&format!("crate{}", path));
+        }
+        if self.edition_2015 && (imported || path.contains("::")) {
+            let alias = self.aliases.get(&format!("crate::{}", path));
+            if alias.is_some() || imported {
+                return alias;
+            }
+        }
+        self.aliases.get(&format!("{}::{}", module, path))
+    }
+
+    /// Returns a type with the aliases expanded, `module` being the module where it is used.
+    fn expand(&self, ty: &str, module: &str, depth: usize) -> String {
+        if depth > MAX_DEPTH {
+            return ty.to_owned();
+        }
+        let mut expanded = ty.to_owned();
+        let mut pos = 0;
+        'outer: loop {
+            for (start, end) in type_paths(&expanded[pos..]) {
+                let (start, end) = (pos + start, pos + end);
+                let alias = match self.lookup(&expanded[start..end], module) {
+                    Some(alias) => alias,
+                    None => continue,
+                };
+                let (args, args_end) = match generic_args(&expanded[end..]) {
+                    Some((args, len)) => (args, end + len),
+                    None => (Vec::new(), end),
+                };
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|arg| self.expand(arg, module, depth + 1))
+                    .collect();
+                let subtype = match alias.substitute(&args) {
+                    Some(subtype) => subtype,
+                    // not a use of the alias, like `Result<T, E>` for `type Result<T>`
+                    None => continue,
+                };
+                let replacement = self.expand(&subtype, &alias.module, depth + 1);
+                expanded.replace_range(start..args_end, &replacement);
+                pos = start + replacement.len();
+                continue 'outer;
+            }
+            break;
+        }
+        expanded
+    }
+}
+
+impl Alias {
+    /// Returns the aliased type with the generic parameters replaced by `args`, or `None` if
+    /// the arguments do not match the parameters.
+    fn substitute(&self, args: &[String]) -> Option<String> {
+        let (lifetimes, types): (Vec<&String>, Vec<&String>) =
+            args.iter().partition(|arg| arg.starts_with('\''));
+        let (lifetime_params, type_params): (Vec<_>, Vec<_>) =
+            self.params.iter().partition(|p| p.0.starts_with('\''));
+        if types.len() > type_params.len()
+            || (!lifetimes.is_empty() && lifetimes.len() != lifetime_params.len())
+        {
+            return None;
+        }
+        let mut values = HashMap::new();
+        for (idx, (name, default)) in type_params.iter().enumerate() {
+            let value = match types.get(idx) {
+                Some(value) => (*value).clone(),
+                None => default.clone()?,
+            };
+            values.insert(name.as_str(), value);
+        }
+        for (idx, (name, _)) in lifetime_params.iter().enumerate() {
+            // elided lifetimes
+            let value = lifetimes
+                .get(idx)
+                .map(|l| (*l).clone())
+                .unwrap_or_else(|| "'_".to_owned());
+            values.insert(name.as_str(), value);
+        }
+        Some(substitute_params(&self.subtype, &values))
+    }
+}
+
+fn expand_module(module: &mut JsonValue, path: &str, aliases: &Aliases) {
+    for item in module["items"].members_mut() {
+        if item["type"] == "mod" {
+            let path = format!("{}::{}", path, item["name"]);
+            expand_module(item, &path, aliases);
+        } else {
+            expand_item(item, path, aliases);
+        }
+    }
+}
+
+fn expand_item(item: &mut JsonValue, module: &str, aliases: &Aliases) {
+    for key in &[
+        "output",
+        "subtype",
+        "generics",
+        "where",
+        "impl_type",
+        "trait",
+    ] {
+        expand_key(item, key, module, aliases);
+    }
+    // indexing a missing key would insert it
+    if item.has_key("inputs") {
+        for input in item["inputs"].members_mut() {
+            expand_key(input, "type", module, aliases);
+        }
+    }
+    if item.has_key("typarambounds") {
+        for bound in item["typarambounds"].members_mut() {
+            expand_key(bound, "trait_ref", module, aliases);
+        }
+    }
+    if item.has_key("fields") {
+        for field in item["fields"].members_mut() {
+            if field.has_key("fields") {
+                // enum variant
+                for variant_field in field["fields"].members_mut() {
+                    expand_key(variant_field, "type", module, aliases);
+                }
+            } else {
+                expand_key(field, "type", module, aliases);
+            }
+        }
+    }
+    if item.has_key("items") {
+        for child in item["items"].members_mut() {
+            expand_item(child, module, aliases);
+        }
+    }
+}
+
+fn expand_key(json: &mut JsonValue, key: &str, module: &str, aliases: &Aliases) {
+    let expanded = match value(json, key) {
+        Some(ty) => aliases.expand(ty, module, 0),
+        None => return,
+    };
+    if value(json, key) != Some(expanded.as_str()) {
+        json[format!("resolved_{}", key)] = JsonValue::String(expanded);
+    }
+}
+
+/// Returns the canonical form of a key if it is known, or its written form.
+pub fn value<'a>(json: &'a JsonValue, key: &str) -> Option<&'a str> {
+    json[format!("resolved_{}", key).as_str()]
+        .as_str()
+        .or_else(|| json[key].as_str())
+}
+
+/// Returns the path of the item `path` used in `module`, for paths starting with `crate`,
+/// `self` or `super`.
+fn relative_path(path: &str, module: &str) -> String {
+    let mut segments: Vec<&str> = module.split("::").collect();
+    let mut rest = path.split("::").peekable();
+    loop {
+        match rest.peek() {
+            Some(&"crate") => segments.truncate(1),
+            Some(&"self") => (),
+            Some(&"super") => {
+                if segments.len() > 1 {
+                    segments.pop();
+                }
+            }
+            _ => break,
+        }
+        rest.next();
+    }
+    segments.extend(rest);
+    segments.join("::")
+}
+
+/// Returns the names and default values of generic parameters, like `<'a, T: Clone = u32>`.
+///
+/// Const parameters are ignored.
+fn generic_params(generics: &str) -> Vec<(String, Option<String>)> {
+    let generics = generics.trim();
+    if !generics.starts_with('<') || !generics.ends_with('>') {
+        return Vec::new();
+    }
+    split_top_level(&generics[1..generics.len() - 1])
+        .into_iter()
+        .filter(|param| !param.starts_with("const "))
+        .map(|param| {
+            let name_end = param
+                .find(|c: char| c == ':' || c == '=' || c.is_whitespace())
+                .unwrap_or(param.len());
+            let default = param
+                .find('=')
+                .map(|idx| param[idx + 1..].trim().to_owned());
+            (param[..name_end].to_owned(), default)
+        })
+        .collect()
+}
+
+/// Returns the generic arguments at the start of `s`, like `<T, 'a>`, and their length.
+fn generic_args(s: &str) -> Option<(Vec<String>, usize)> {
+    if !s.starts_with('<') {
+        return None;
+    }
+    let mut depth = 0;
+    let mut prev = ' ';
+    for (idx, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            // `->` in `Fn(T) -> U`
+            '>' if prev != '-' => {
+                depth -= 1;
+                if depth == 0 {
+                    let args = split_top_level(&s[1..idx]);
+                    return Some((args, idx + 1));
+                }
+            }
+            _ => (),
+        }
+        prev = c;
+    }
+    None
+}
+
+/// Split a list of generic parameters or arguments at the top-level commas.
+pub fn split_top_level(s: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut prev = ' ';
+    let mut start = 0;
+    for (idx, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if prev == '-' => (),
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..idx].trim().to_owned());
+                start = idx + 1;
+            }
+            _ => (),
+        }
+        prev = c;
+    }
+    if !s[start..].trim().is_empty() {
+        parts.push(s[start..].trim().to_owned());
+    }
+    parts
+}
+
+/// Replace the generic parameters (`T`, `T::Item`, `'a`) of a type by their values.
+fn substitute_params(ty: &str, values: &HashMap<&str, String>) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut result = String::new();
+    let mut last = 0;
+    let mut positions = type_paths(ty);
+    // lifetimes are not paths
+    let mut chars = ty.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c == '\'' {
+            let mut end = idx + 1;
+            while let Some(&(i, c)) = chars.peek() {
+                if !is_ident(c) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            positions.push((idx, end));
+        }
+    }
+    positions.sort();
+    for (start, end) in positions {
+        let path = &ty[start..end];
+        let first_end = path.find("::").unwrap_or(path.len());
+        if let Some(value) = values.get(&path[..first_end]) {
+            result.push_str(&ty[last..start]);
+            if first_end < path.len() {
+                // associated type of a parameter
+                result.push_str(&format!("<{}>", value));
+            } else {
+                result.push_str(value);
+            }
+            last = start + first_end;
+        }
+    }
+    result.push_str(&ty[last..]);
+    result
+}
+
+/// Returns the positions of the paths used in a type, like `bytes::Bytes` and `Vec` in
+/// `Vec<bytes::Bytes>`.
+fn type_paths(ty: &str) -> Vec<(usize, usize)> {
+    let mut paths = Vec::new();
+    let chars: Vec<(usize, char)> = ty.char_indices().collect();
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let at_separator = |i: usize| {
+        i + 2 < chars.len()
+            && chars[i].1 == ':'
+            && chars[i + 1].1 == ':'
+            && is_ident(chars[i + 2].1)
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        if c == '"' {
+            // ABI strings
+            i += 1;
+            while i < chars.len() && chars[i].1 != '"' {
+                i += 1;
+            }
+            i += 1;
+        } else if c == '\'' || c.is_numeric() {
+            // lifetimes and array lengths
+            i += 1;
+            while i < chars.len() && is_ident(chars[i].1) {
+                i += 1;
+            }
+        } else if is_ident(c) || (at_separator(i) && (i == 0 || !is_path_end(chars[i - 1].1))) {
+            let start = chars[i].0;
+            if c == ':' {
+                i += 2;
+            }
+            loop {
+                while i < chars.len() && is_ident(chars[i].1) {
+                    i += 1;
+                }
+                if at_separator(i) {
+                    i += 2;
+                } else {
+                    break;
+                }
+            }
+            let end = if i < chars.len() {
+                chars[i].0
+            } else {
+                ty.len()
+            };
+            paths.push((start, end));
+        } else {
+            i += 1;
+        }
+    }
+    paths
+}
+
+/// `<T as Trait>::Item` is not an absolute path.
+fn is_path_end(c: char) -> bool {
+    c == '>' || c.is_alphanumeric() || c == '_'
+}
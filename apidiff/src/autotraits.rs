@@ -0,0 +1,20 @@
+//! Auto traits (`Send`, `Sync`, `Unpin`) inferred by `apicheck --auto-traits`.
+//!
+//! The inference is a structural heuristic, so the auto traits which a type loses are
+//! reported as heuristic.
+
+use json::JsonValue;
+
+/// Returns the auto traits of the old version of a type which are not inferred for the new
+/// version.
+pub fn lost_auto_traits(json1: &JsonValue, json2: &JsonValue) -> Vec<String> {
+    if !json1.has_key("auto_traits") || !json2.has_key("auto_traits") {
+        return Vec::new();
+    }
+    json1["auto_traits"]
+        .members()
+        .filter(|t| !json2["auto_traits"].members().any(|t2| t2 == *t))
+        .filter_map(|t| t.as_str())
+        .map(String::from)
+        .collect()
+}
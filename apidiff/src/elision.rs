@@ -0,0 +1,365 @@
+//! Lifetime elision: `fn get<'a>(&'a self) -> &'a str` and `fn get(&self) -> &str` are the
+//! same signature.
+//!
+//! Signatures of functions and methods are compared with their elided lifetimes made
+//! explicit, and with the lifetime parameters of the function renamed in order of
+//! appearance. Lifetimes declared by an enclosing impl or trait are kept as written.
+
+use json::JsonValue;
+use std::collections::HashMap;
+
+use aliases::{split_top_level, value};
+
+/// A signature with its elided lifetimes made explicit.
+struct Signature {
+    generics: String,
+    inputs: Vec<String>,
+    output: String,
+    where_clause: String,
+}
+
+/// Make the lifetimes of the signatures of a description, or of each crate of a workspace
+/// description, explicit.
+///
+/// The normalized signatures are stored in the `resolved_` keys, like the canonical types
+/// extracted with `--resolve-paths`.
+pub fn normalize_lifetimes(json: &mut JsonValue) {
+    if json.has_key("crates") {
+        for (_, krate) in json["crates"].entries_mut() {
+            normalize_lifetimes(krate);
+        }
+        return;
+    }
+    for module in json["modules"].members_mut() {
+        normalize_items(module);
+    }
+}
+
+fn normalize_items(json: &mut JsonValue) {
+    // indexing a missing key would insert it
+    if !json.has_key("items") {
+        return;
+    }
+    for item in json["items"].members_mut() {
+        match item["type"].as_str() {
+            Some("function") | Some("method") => normalize_function(item),
+            _ => normalize_items(item),
+        }
+    }
+}
+
+fn normalize_function(item: &mut JsonValue) {
+    if !item.has_key("inputs") {
+        return;
+    }
+    let sig = normalized_signature(item);
+    set_resolved(item, "generics", sig.generics);
+    set_resolved(item, "where", sig.where_clause);
+    set_resolved(item, "output", sig.output);
+    for (input, ty) in item["inputs"].members_mut().zip(sig.inputs) {
+        set_resolved(input, "type", ty);
+    }
+}
+
+fn set_resolved(json: &mut JsonValue, key: &str, normalized: String) {
+    if json.has_key(key) && value(json, key) != Some(normalized.as_str()) {
+        json[format!("resolved_{}", key)] = JsonValue::String(normalized);
+    }
+}
+
+fn normalized_signature(item: &JsonValue) -> Signature {
+    let params = generic_params(value(item, "generics").unwrap_or(""));
+    let mut fresh = 0;
+    let inputs: Vec<String> = item["inputs"]
+        .members()
+        .map(|input| elaborate(value(input, "type").unwrap_or(""), None, &mut fresh))
+        .collect();
+    // the elided lifetimes of the output are the lifetime of `&self`, or the only lifetime of
+    // the inputs
+    let mut input_lifetimes: Vec<String> = Vec::new();
+    for ty in &inputs {
+        for lifetime in lifetimes(ty) {
+            if !input_lifetimes.contains(&lifetime) {
+                input_lifetimes.push(lifetime);
+            }
+        }
+    }
+    let self_lifetime = match item["inputs"].members().next() {
+        Some(receiver) if receiver["name"] == "self" && inputs[0].starts_with('&') => {
+            lifetimes(&inputs[0]).into_iter().next()
+        }
+        _ => None,
+    };
+    let output_lifetime = self_lifetime.or_else(|| {
+        if input_lifetimes.len() == 1 {
+            input_lifetimes.pop()
+        } else {
+            None
+        }
+    });
+    let output = elaborate(
+        value(item, "output").unwrap_or(""),
+        output_lifetime.as_deref(),
+        &mut fresh,
+    );
+    let where_clause = value(item, "where").unwrap_or("").to_owned();
+
+    // rename the lifetimes of the function in order of appearance
+    let own: Vec<String> = params
+        .iter()
+        .filter(|p| p.starts_with('\''))
+        .map(|p| p.split(':').next().unwrap_or("").trim().to_owned())
+        .collect();
+    let mut names = HashMap::new();
+    let all = inputs
+        .iter()
+        .chain(Some(&output))
+        .chain(&params)
+        .chain(Some(&where_clause));
+    for ty in all {
+        for lifetime in lifetimes(ty) {
+            if (own.contains(&lifetime) || is_fresh(&lifetime)) && !names.contains_key(&lifetime) {
+                let name = format!("'_{}", names.len());
+                names.insert(lifetime, name);
+            }
+        }
+    }
+    // lifetime parameters without bounds are declared implicitly by elided lifetimes
+    let params: Vec<String> = params
+        .iter()
+        .filter(|p| !p.starts_with('\'') || p.contains(':'))
+        .map(|p| rename_lifetimes(p, &names))
+        .collect();
+    let generics = if params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", params.join(", "))
+    };
+    Signature {
+        generics,
+        inputs: inputs
+            .iter()
+            .map(|ty| rename_lifetimes(ty, &names))
+            .collect(),
+        output: rename_lifetimes(&output, &names),
+        where_clause: rename_lifetimes(&where_clause, &names),
+    }
+}
+
+/// Returns a description of a change of the lifetimes of a signature, which is not a change
+/// of its elided or explicit form.
+pub fn lifetime_change(json1: &JsonValue, json2: &JsonValue) -> Option<String> {
+    let written1 = signature(json1, |json, key| json[key].as_str());
+    let written2 = signature(json2, |json, key| json[key].as_str());
+    let normalized1 = signature(json1, value);
+    let normalized2 = signature(json2, value);
+    if written1 == written2
+        || normalized1 == normalized2
+        || erase_lifetimes(&normalized1) != erase_lifetimes(&normalized2)
+    {
+        return None;
+    }
+    Some(format!(
+        "'{}' is not equivalent to '{}'",
+        written1, written2
+    ))
+}
+
+fn signature<'a, F>(json: &'a JsonValue, get: F) -> String
+where
+    F: Fn(&'a JsonValue, &str) -> Option<&'a str>,
+{
+    let inputs: Vec<String> = json["inputs"]
+        .members()
+        .map(|input| format!("{}: {}", input["name"], get(input, "type").unwrap_or("")))
+        .collect();
+    let mut sig = format!(
+        "fn {}{}({})",
+        json["name"],
+        get(json, "generics").unwrap_or(""),
+        inputs.join(", ")
+    );
+    match get(json, "output") {
+        Some(output) if !output.is_empty() => sig.push_str(&format!(" -> {}", output)),
+        _ => (),
+    }
+    sig.push_str(get(json, "where").unwrap_or(""));
+    sig
+}
+
+/// Returns the parameters of a `<...>` list.
+fn generic_params(generics: &str) -> Vec<String> {
+    let generics = generics.trim();
+    if !generics.starts_with('<') || !generics.ends_with('>') {
+        return Vec::new();
+    }
+    split_top_level(&generics[1..generics.len() - 1])
+}
+
+/// Returns a type with its elided lifetimes (`&T`, `'_`) replaced by `lifetime`, or by fresh
+/// lifetimes.
+///
+/// The elided lifetimes of `fn(&T)` and `Fn(&T)` types are not replaced, they are elided in
+/// the signature of the function type.
+fn elaborate(ty: &str, lifetime: Option<&str>, fresh: &mut usize) -> String {
+    let mut next = || match lifetime {
+        Some(lifetime) => lifetime.to_owned(),
+        None => {
+            *fresh += 1;
+            format!("'__{}", *fresh - 1)
+        }
+    };
+    let excluded = fn_types(ty);
+    let mut result = String::new();
+    let mut chars = ty.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if excluded
+            .iter()
+            .any(|&(start, end)| idx >= start && idx < end)
+        {
+            result.push(c);
+            continue;
+        }
+        match c {
+            '&' => {
+                result.push('&');
+                if chars.peek().map(|&(_, c)| c) != Some('\'') {
+                    result.push_str(&next());
+                    result.push(' ');
+                }
+            }
+            '\'' => {
+                let mut name = String::from("'");
+                while let Some(&(_, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                if name == "'_" {
+                    result.push_str(&next());
+                } else {
+                    result.push_str(&name);
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Fresh lifetimes are only used during normalization.
+fn is_fresh(lifetime: &str) -> bool {
+    lifetime.starts_with("'__")
+}
+
+/// Returns the positions of the function types (`fn(&T) -> &U`, `Fn(&T)`) in a type.
+fn fn_types(ty: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    for keyword in &["fn(", "Fn(", "FnMut(", "FnOnce("] {
+        for (start, _) in ty.match_indices(keyword) {
+            if ty[..start].chars().next_back().is_some_and(is_ident) {
+                continue;
+            }
+            let mut end = match closing(ty, start + keyword.len(), false) {
+                Some(end) => end,
+                None => continue,
+            };
+            if ty[end..].starts_with(" -> ") {
+                end += 4;
+                end += closing(&ty[end..], 0, true)
+                    .map(|len| len - 1)
+                    .unwrap_or_else(|| ty.len() - end);
+            }
+            ranges.push((start, end));
+        }
+    }
+    ranges
+}
+
+/// Returns the position after the delimiter closing a list starting at `start`, or after
+/// the delimiter ending a type if `is_type`.
+fn closing(s: &str, start: usize, is_type: bool) -> Option<usize> {
+    let mut depth = 0;
+    let mut prev = ' ';
+    for (idx, c) in s[start..].char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if prev == '-' => (),
+            ',' if depth == 0 && is_type => return Some(start + idx + 1),
+            '>' | ')' | ']' if depth == 0 => return Some(start + idx + 1),
+            '>' | ')' | ']' => depth -= 1,
+            _ => (),
+        }
+        prev = c;
+    }
+    None
+}
+
+/// Returns the lifetimes used in a type, outside function types.
+fn lifetimes(ty: &str) -> Vec<String> {
+    let excluded = fn_types(ty);
+    let mut lifetimes = Vec::new();
+    let mut chars = ty.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c != '\''
+            || excluded
+                .iter()
+                .any(|&(start, end)| idx >= start && idx < end)
+        {
+            continue;
+        }
+        let mut name = String::from("'");
+        while let Some(&(_, c)) = chars.peek() {
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        lifetimes.push(name);
+    }
+    lifetimes
+}
+
+fn rename_lifetimes(ty: &str, names: &HashMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut chars = ty.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            result.push(c);
+            continue;
+        }
+        let mut name = String::from("'");
+        while let Some(&c) = chars.peek() {
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        result.push_str(names.get(&name).unwrap_or(&name));
+    }
+    result
+}
+
+/// Replace all the lifetimes of a signature by `'_`.
+fn erase_lifetimes(sig: &str) -> String {
+    let mut result = String::new();
+    let mut chars = sig.chars().peekable();
+    while let Some(c) = chars.next() {
+        result.push(c);
+        if c == '\'' {
+            result.push('_');
+            while chars
+                .peek()
+                .is_some_and(|&c| c.is_alphanumeric() || c == '_')
+            {
+                chars.next();
+            }
+        }
+    }
+    result
+}
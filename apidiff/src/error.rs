@@ -1,4 +1,5 @@
 use json;
+use std::fmt;
 use std::io;
 use std::str;
 
@@ -7,6 +8,21 @@ pub enum ApiDiffError {
     IoError(io::Error),
     JsonError(json::Error),
     Utf8Error(str::Utf8Error),
+    /// Running apicheck on a crate failed
+    ExtractError(String),
+}
+
+impl fmt::Display for ApiDiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApiDiffError::IoError(ref e) => write!(f, "io error: {}", e),
+            ApiDiffError::JsonError(ref e) => write!(f, "invalid JSON: {}", e),
+            ApiDiffError::Utf8Error(ref e) => write!(f, "invalid UTF-8: {}", e),
+            ApiDiffError::ExtractError(ref stderr) => {
+                write!(f, "apicheck failed:\n{}", stderr.trim_end())
+            }
+        }
+    }
 }
 
 impl From<io::Error> for ApiDiffError {
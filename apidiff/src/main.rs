@@ -1,3 +1,4 @@
+extern crate apicheck_common;
 extern crate clap;
 use clap::{crate_version, App, Arg};
 
@@ -6,48 +7,124 @@ extern crate json;
 extern crate log;
 extern crate env_logger;
 
+use apicheck_common::filter::ItemFilter;
 use json::JsonValue;
 use std::collections;
+use std::env;
 use std::fs;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str;
 
+mod abi;
+
+mod aliases;
+
+mod autotraits;
+
+mod elision;
+
 mod error;
 use error::ApiDiffError;
 
+mod opaque;
+
+mod semver;
+use semver::Verdict;
+
+mod unsafety;
+
 pub struct Config {
     _verbose: bool,
     strip: usize,
+    /// Feature sets present in both descriptions, if they were extracted with a feature matrix
+    feature_sets: Option<Vec<Vec<String>>>,
+}
+
+/// Semver verdict for a crate of a workspace.
+pub struct CrateVerdict {
+    pub name: String,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    /// Version bump required by the API changes
+    pub verdict: Verdict,
 }
 
 pub struct DiffReport {
+    pub crates_added: u32,
+    pub crates_removed: u32,
+    pub crates: Vec<CrateVerdict>,
     pub mods_added: u32,
     pub mods_removed: u32,
     pub mods_changed: u32,
     pub items_added: u32,
     pub items_removed: u32,
     pub items_changed: u32,
+    pub macros_changed: u32,
+    /// Items whose ABI has changed (layout of `#[repr]` types, exported functions)
+    pub abi_changed: u32,
+    /// Items which add unsafe surface (`unsafe` items, `static mut`, raw pointers)
+    pub unsafe_added: u32,
+    /// Crates added to or removed from the public dependencies
+    pub public_deps_changed: u32,
+    /// Types which may have lost an auto trait (`Send`, `Sync`, `Unpin`), a heuristic
+    pub auto_traits_lost: u32,
+    /// Items which could not be compared
+    pub errors: u32,
 }
 
 impl DiffReport {
     pub fn new() -> DiffReport {
         DiffReport {
+            crates_added: 0,
+            crates_removed: 0,
+            crates: Vec::new(),
             mods_added: 0,
             mods_removed: 0,
             mods_changed: 0,
             items_added: 0,
             items_removed: 0,
             items_changed: 0,
+            macros_changed: 0,
+            abi_changed: 0,
+            unsafe_added: 0,
+            public_deps_changed: 0,
+            auto_traits_lost: 0,
+            errors: 0,
         }
     }
 
     pub fn has_changes(&self) -> bool {
-        self.mods_added != 0
+        self.crates_added != 0
+            || self.crates_removed != 0
+            || self.mods_added != 0
             || self.mods_removed != 0
             || self.mods_changed != 0
             || self.items_added != 0
             || self.items_removed != 0
             || self.items_changed != 0
+            || self.macros_changed != 0
+            || self.abi_changed != 0
+            || self.unsafe_added != 0
+            || self.public_deps_changed != 0
+            || self.auto_traits_lost != 0
+    }
+
+    /// Add the counters of `other` to this report.
+    pub fn add(&mut self, other: &DiffReport) {
+        self.mods_added += other.mods_added;
+        self.mods_removed += other.mods_removed;
+        self.mods_changed += other.mods_changed;
+        self.items_added += other.items_added;
+        self.items_removed += other.items_removed;
+        self.items_changed += other.items_changed;
+        self.macros_changed += other.macros_changed;
+        self.abi_changed += other.abi_changed;
+        self.unsafe_added += other.unsafe_added;
+        self.public_deps_changed += other.public_deps_changed;
+        self.auto_traits_lost += other.auto_traits_lost;
+        self.errors += other.errors;
     }
 }
 
@@ -70,15 +147,53 @@ fn main() {
                 .long("strip")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("include")
+                .help("Compare only the items whose path matches a pattern, like crate::ffi::**")
+                .long("include")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .help("Ignore the items whose path matches a pattern")
+                .long("exclude")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("include-kind")
+                .help("Compare only the items of a kind, like function, trait or impl")
+                .long("include-kind")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("exclude-kind")
+                .help("Ignore the items of a kind")
+                .long("exclude-kind")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("crate-name")
+                .help("Name of the crate, used in paths (default: name recorded in the files)")
+                .long("crate-name")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("FILE1")
-                .help("First file name")
+                .help("First file name (JSON, crate directory or .crate archive)")
                 .required(true)
                 .index(1),
         )
         .arg(
             Arg::with_name("FILE2")
-                .help("Second file name")
+                .help("Second file name (JSON, crate directory or .crate archive)")
                 .required(true)
                 .index(2),
         )
@@ -93,25 +208,76 @@ fn main() {
     } else {
         0
     };
-
-    let config = Config {
-        _verbose: verbose,
-        strip,
-    };
+    let mut filter = ItemFilter::new();
+    for pattern in matches.values_of("include").into_iter().flatten() {
+        filter.include(pattern);
+    }
+    for pattern in matches.values_of("exclude").into_iter().flatten() {
+        filter.exclude(pattern);
+    }
+    for kind in matches.values_of("include-kind").into_iter().flatten() {
+        filter.include_kind(kind);
+    }
+    for kind in matches.values_of("exclude-kind").into_iter().flatten() {
+        filter.exclude_kind(kind);
+    }
 
     // Work !
     let mut report = DiffReport::new();
-    let json1 = read_json(&input1).unwrap();
-    let json2 = read_json(&input2).unwrap();
-    // XXX
-    let _ = compare_json(&json1, &json2, &config, &mut report);
+    let mut json1 = read_json_or_exit(input1);
+    let mut json2 = read_json_or_exit(input2);
+    filter_json(&mut json1, &filter);
+    filter_json(&mut json2, &filter);
+    if resolves_paths(&json1) != resolves_paths(&json2) {
+        warn!(
+            "only one description was extracted with --resolve-paths, types written \
+             differently will be reported as changes"
+        );
+    }
+    let crate_name = matches.value_of("crate-name");
+    aliases::expand_aliases(&mut json1, crate_name);
+    aliases::expand_aliases(&mut json2, crate_name);
+    elision::normalize_lifetimes(&mut json1);
+    elision::normalize_lifetimes(&mut json2);
+    opaque::normalize_return_types(&mut json1);
+    opaque::normalize_return_types(&mut json2);
+
+    if json1.has_key("crates") || json2.has_key("crates") {
+        compare_crates(&json1, &json2, verbose, strip, &mut report);
+    } else {
+        let config = Config {
+            _verbose: verbose,
+            strip,
+            feature_sets: common_feature_sets(&json1, &json2),
+        };
+        // XXX
+        let _ = compare_json(&json1, &json2, &config, &mut report);
+    }
     show_report(&report);
 
-    let rc = if report.has_changes() { 1 } else { 0 };
+    let rc = if report.errors != 0 {
+        2
+    } else if report.has_changes() {
+        1
+    } else {
+        0
+    };
     ::std::process::exit(rc);
 }
 
+/// Read a description (or extract the API of a crate), and exit with the error code if it
+/// fails.
+fn read_json_or_exit(input: &str) -> JsonValue {
+    read_json(input).unwrap_or_else(|e| {
+        eprintln!("apidiff: cannot read {}: {}", input, e);
+        ::std::process::exit(2);
+    })
+}
+
 fn read_json(input: &str) -> Result<JsonValue, ApiDiffError> {
+    if is_crate(Path::new(input)) {
+        return extract_json(input);
+    }
     let mut f = fs::OpenOptions::new().read(true).open(input)?;
 
     let sz = f.metadata().map(|m| m.len() as usize + 1)?;
@@ -124,6 +290,94 @@ fn read_json(input: &str) -> Result<JsonValue, ApiDiffError> {
     Ok(json)
 }
 
+/// Returns `true` if the description (or a crate of the workspace description) was extracted
+/// with `--resolve-paths`, and has the canonical form of types.
+fn resolves_paths(json: &JsonValue) -> bool {
+    json["resolve_paths"] == true
+        || json["crates"]
+            .entries()
+            .any(|(_, krate)| krate["resolve_paths"] == true)
+}
+
+/// Apply `filter` to a description, or to each crate of a workspace description.
+fn filter_json(json: &mut JsonValue, filter: &ItemFilter) {
+    if json.has_key("crates") {
+        for (_, krate) in json["crates"].entries_mut() {
+            filter.apply(krate);
+        }
+    } else {
+        filter.apply(json);
+    }
+}
+
+/// Compare two workspace descriptions (produced by `cargo apicheck --workspace`), crate by crate.
+fn compare_crates(
+    json1: &JsonValue,
+    json2: &JsonValue,
+    verbose: bool,
+    strip: usize,
+    report: &mut DiffReport,
+) {
+    let names: collections::BTreeSet<&str> = json1["crates"]
+        .entries()
+        .chain(json2["crates"].entries())
+        .map(|(name, _)| name)
+        .collect();
+    for name in names {
+        let crate1 = &json1["crates"][name];
+        let crate2 = &json2["crates"][name];
+        if crate2.is_null() {
+            info!("Removed crate: {}", name);
+            report.crates_removed += 1;
+            continue;
+        }
+        if crate1.is_null() {
+            info!("Added crate: {}", name);
+            report.crates_added += 1;
+            continue;
+        }
+        let config = Config {
+            _verbose: verbose,
+            strip,
+            feature_sets: common_feature_sets(crate1, crate2),
+        };
+        let mut crate_report = DiffReport::new();
+        compare_json(crate1, crate2, &config, &mut crate_report);
+        report.add(&crate_report);
+        report.crates.push(CrateVerdict {
+            name: name.to_owned(),
+            old_version: crate1["version"].as_str().map(|s| s.to_owned()),
+            new_version: crate2["version"].as_str().map(|s| s.to_owned()),
+            verdict: Verdict::from_report(&crate_report),
+        });
+    }
+}
+
+/// Returns `true` if `path` is a crate directory or a packaged crate, instead of a JSON file.
+fn is_crate(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    path.is_dir() || name.ends_with(".crate") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// Extract the API of a crate by running `apicheck` (installed next to this program, or in
+/// the `PATH`).
+fn extract_json(input: &str) -> Result<JsonValue, ApiDiffError> {
+    let program = env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name("apicheck"))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from("apicheck"));
+    let output = Command::new(program).arg(input).output()?;
+    if !output.status.success() {
+        return Err(ApiDiffError::ExtractError(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+    let s = str::from_utf8(&output.stdout)?;
+    let json = json::parse(s)?;
+    Ok(json)
+}
+
 fn compare_json(
     json1: &JsonValue,
     json2: &JsonValue,
@@ -166,12 +420,45 @@ fn compare_json(
     for m in h2.difference(&h1) {
         info!("Added module: {}", m);
         report.mods_added += 1;
+        for item in hm2[m]["items"].members() {
+            report_unsafe_surface(item, &mut report);
+        }
     }
     for m in h1.intersection(&h2) {
         let js1 = hm1[m];
         let js2 = hm2[m];
         compare_modules(js1, js2, config, &mut report);
     }
+    compare_public_dependencies(json1, json2, &mut report);
+}
+
+/// Compare the dependencies exposed in the API (extracted with `--public-dependencies`).
+///
+/// Upgrading a public dependency to an incompatible version is a breaking change, so new
+/// public dependencies are reported.
+fn compare_public_dependencies(json1: &JsonValue, json2: &JsonValue, report: &mut DiffReport) {
+    let deps1 = &json1["public_dependencies"];
+    let deps2 = &json2["public_dependencies"];
+    if deps1.is_null() || deps2.is_null() {
+        return;
+    }
+    for (krate, items) in deps2.entries() {
+        if !deps1.has_key(krate) {
+            let items: Vec<&str> = items.members().filter_map(|i| i.as_str()).collect();
+            warn!(
+                "New public dependency: '{}', exposed by {}",
+                krate,
+                items.join(", ")
+            );
+            report.public_deps_changed += 1;
+        }
+    }
+    for (krate, _) in deps1.entries() {
+        if !deps2.has_key(krate) {
+            info!("Removed public dependency: '{}'", krate);
+            report.public_deps_changed += 1;
+        }
+    }
 }
 
 fn compare_modules(
@@ -180,37 +467,61 @@ fn compare_modules(
     config: &Config,
     mut report: &mut DiffReport,
 ) -> bool {
+    // an item differing between feature sets has one entry per variant
     let mut h1 = collections::HashSet::new();
     let mut hm1 = collections::HashMap::new();
     for member in json1["items"].members() {
-        let path = member["name"].as_str().unwrap();
-        let obj = member;
-        h1.insert(path);
-        hm1.insert(path, obj);
+        if member["type"] == "macro" {
+            continue;
+        }
+        let path = item_key(member);
+        h1.insert(path.clone());
+        hm1.entry(path).or_insert_with(Vec::new).push(member);
     }
     let mut h2 = collections::HashSet::new();
     let mut hm2 = collections::HashMap::new();
     for member in json2["items"].members() {
-        let path = member["name"].as_str().unwrap();
-        let obj = member;
-        h2.insert(path);
-        hm2.insert(path, obj);
+        if member["type"] == "macro" {
+            continue;
+        }
+        let path = item_key(member);
+        h2.insert(path.clone());
+        hm2.entry(path).or_insert_with(Vec::new).push(member);
     }
     // look for differences
     for m in h1.difference(&h2) {
-        info!("Removed item: '{}'", m);
+        info!("Removed item: '{}'{}", m, location_suffix(hm1[m][0]));
         report.items_removed += 1;
     }
     for m in h2.difference(&h1) {
-        info!("Added item: '{}'", m);
+        info!("Added item: '{}'{}", m, location_suffix(hm2[m][0]));
         report.items_added += 1;
+        for variant in &hm2[m] {
+            report_unsafe_surface(variant, &mut report);
+        }
     }
-    let mut changed = false;
+    let mut changed = compare_macros(json1, json2, &mut report);
+    let features1 = item_features(json1);
+    let features2 = item_features(json2);
     for m in h1.intersection(&h2) {
-        let js1 = hm1[m];
-        let js2 = hm2[m];
         debug!("***");
-        if compare_items(js1, js2, config, &mut report) {
+        let mut item_changed = false;
+        for (js1, js2, set) in variant_pairs(&hm1[m], &hm2[m], config) {
+            if compare_items(js1, js2, config, &mut report) {
+                if let Some(set) = set {
+                    info!(
+                        "Item '{}' has changed with features [{}]",
+                        m,
+                        set.join(", ")
+                    );
+                }
+                item_changed = true;
+            }
+        }
+        if let (Some(f1), Some(f2)) = (features1.get(m), features2.get(m)) {
+            item_changed |= compare_features(m, f1, f2, config);
+        }
+        if item_changed {
             changed = true;
             report.items_changed += 1;
         }
@@ -224,6 +535,85 @@ fn compare_modules(
     }
 }
 
+/// Returns the pairs of variants of an item to compare.
+///
+/// With a feature matrix, an item has one variant per distinct definition, each listing the
+/// feature sets where it is used. The variants used with the same feature set are compared,
+/// and the feature set is returned if the item has several variants.
+fn variant_pairs<'a>(
+    variants1: &[&'a JsonValue],
+    variants2: &[&'a JsonValue],
+    config: &'a Config,
+) -> Vec<(&'a JsonValue, &'a JsonValue, Option<&'a Vec<String>>)> {
+    let common = match config.feature_sets {
+        Some(ref common) if variants1.len() > 1 || variants2.len() > 1 => common,
+        _ => return vec![(variants1[0], variants2[0], None)],
+    };
+    let mut pairs: Vec<(&JsonValue, &JsonValue, Option<&Vec<String>>)> = Vec::new();
+    for set in common {
+        let find = |variants: &[&'a JsonValue]| {
+            variants
+                .iter()
+                .cloned()
+                .find(|v| v["features"].members().any(|s| feature_set(s) == *set))
+        };
+        if let (Some(js1), Some(js2)) = (find(variants1), find(variants2)) {
+            let known = pairs
+                .iter()
+                .any(|&(p1, p2, _)| std::ptr::eq(p1, js1) && std::ptr::eq(p2, js2));
+            if !known {
+                pairs.push((js1, js2, Some(set)));
+            }
+        }
+    }
+    pairs
+}
+
+/// Returns the key identifying an item in its module.
+///
+/// Impl blocks and use declarations have no name, they are identified by the implemented
+/// trait and type, and by the imported path. Items declared in the branches of a `cfg_if!`
+/// invocation share their name, they are also identified by the condition of their branch.
+fn item_key(json: &JsonValue) -> String {
+    let name = json["name"].as_str().unwrap_or("");
+    let key = match json["type"].as_str() {
+        Some("impl") => {
+            let impl_type = resolved_value(json, "impl_type");
+            match resolved_value(json, "trait").as_str() {
+                Some(t) if !t.is_empty() => format!("impl {} for {}", t, impl_type),
+                _ => format!("impl {}", impl_type),
+            }
+        }
+        Some("usetree") if name.is_empty() => match json["kind"].as_str() {
+            Some("") | None => format!("use {}", json["path"]),
+            Some("nested") => format!("use {}::{{..}}", json["path"]),
+            Some("*") => format!("use {}::*", json["path"]),
+            Some(rename) => format!("use {} as {}", json["path"], rename),
+        },
+        _ => name.to_owned(),
+    };
+    match json["cfg"].as_str() {
+        Some(cfg) => format!("{} #[cfg({})]", key, cfg),
+        None => key,
+    }
+}
+
+/// Report the unsafe surface of an added item.
+fn report_unsafe_surface(json: &JsonValue, report: &mut DiffReport) {
+    let parts = unsafety::unsafe_surface(json);
+    for part in &parts {
+        warn!(
+            "New unsafe surface: '{}': {}{}",
+            item_key(json),
+            part,
+            location_suffix(json)
+        );
+    }
+    if !parts.is_empty() {
+        report.unsafe_added += 1;
+    }
+}
+
 fn compare_items(
     json1: &JsonValue,
     json2: &JsonValue,
@@ -231,14 +621,71 @@ fn compare_items(
     mut report: &mut DiffReport,
 ) -> bool {
     let ty1 = &json1["type"];
-    let ty2 = &json1["type"];
+    let ty2 = &json2["type"];
     if ty1 != ty2 {
-        info!("Item {} has changed type", json1["name"].as_str().unwrap());
+        info!(
+            "Item {} has changed type{}",
+            json1["name"].as_str().unwrap(),
+            locations_suffix(json1, json2)
+        );
         return true;
     }
-    match ty1.as_str().unwrap() {
+    let abi_changes = abi::abi_changes(json1, json2);
+    for change in &abi_changes {
+        warn!(
+            "ABI change: '{}': {}{}",
+            item_key(json1),
+            change,
+            locations_suffix(json1, json2)
+        );
+    }
+    if !abi_changes.is_empty() {
+        report.abi_changed += 1;
+    }
+    let unsafe_parts = unsafety::new_unsafe_surface(json1, json2);
+    for part in &unsafe_parts {
+        warn!(
+            "New unsafe surface: '{}': {}{}",
+            item_key(json2),
+            part,
+            location_suffix(json2)
+        );
+    }
+    if !unsafe_parts.is_empty() {
+        report.unsafe_added += 1;
+    }
+    let lost = autotraits::lost_auto_traits(json1, json2);
+    for tr in &lost {
+        warn!(
+            "Auto trait lost (heuristic): '{}' may no longer implement `{}`{}",
+            item_key(json1),
+            tr,
+            locations_suffix(json1, json2)
+        );
+    }
+    if !lost.is_empty() {
+        report.auto_traits_lost += 1;
+    }
+    for change in opaque::return_changes(json1, json2) {
+        warn!(
+            "Return type change: '{}': {}{}",
+            item_key(json1),
+            change,
+            locations_suffix(json1, json2)
+        );
+    }
+    if let Some(change) = elision::lifetime_change(json1, json2) {
+        warn!(
+            "Lifetime change: '{}': {}{}",
+            item_key(json1),
+            change,
+            locations_suffix(json1, json2)
+        );
+    }
+    let changed = match ty1.as_str().unwrap() {
         "function" => compare_item_keys(json1, json2, FN_KEYS),
         "struct" => compare_item_keys(json1, json2, STRUCT_KEYS),
+        "union" => compare_item_keys(json1, json2, STRUCT_KEYS),
         "enum" => compare_item_keys(json1, json2, STRUCT_KEYS),
         "mod" => compare_modules(json1, json2, config, &mut report),
         "trait" => compare_traits(json1, json2, config, &mut report),
@@ -248,17 +695,162 @@ fn compare_items(
         "const" => compare_item_keys(json1, json2, CONST_KEYS),
         "static" => compare_item_keys(json1, json2, STATIC_KEYS),
         "usetree" => compare_item_keys(json1, json2, USETREE_KEYS),
-        _e => {
-            warn!("unsupported item type '{}'", _e);
-            false
+        "extern_crate" => compare_item_keys(json1, json2, EXTERN_CRATE_KEYS),
+        ty => {
+            // the item cannot be compared, do not report it as unchanged
+            error!(
+                "Item '{}' has an unsupported type '{}'{}",
+                item_key(json1),
+                ty,
+                locations_suffix(json1, json2)
+            );
+            report.errors += 1;
+            true
+        }
+    };
+    changed || !abi_changes.is_empty() || !lost.is_empty()
+}
+
+/// Compare unexpanded macro invocations.
+///
+/// The items defined by a macro invocation are unknown, so any change in the invocations of a
+/// macro is reported as unverifiable.
+fn compare_macros(json1: &JsonValue, json2: &JsonValue, report: &mut DiffReport) -> bool {
+    let invocations1 = macro_invocations(json1);
+    let invocations2 = macro_invocations(json2);
+    let mut changed = false;
+    let paths: collections::BTreeSet<_> = invocations1.keys().chain(invocations2.keys()).collect();
+    for path in paths {
+        let (fingerprints1, locations1) = invocations1.get(path).cloned().unwrap_or_default();
+        let (fingerprints2, locations2) = invocations2.get(path).cloned().unwrap_or_default();
+        if fingerprints1 != fingerprints2 {
+            warn!(
+                "Invocations of macro '{}' have changed (old: [{}], new: [{}]): unverifiable, review manually",
+                path,
+                locations1.join(", "),
+                locations2.join(", ")
+            );
+            report.macros_changed += 1;
+            changed = true;
         }
     }
+    changed
+}
+
+/// Collect, for each macro path, the sorted fingerprints and the locations of its invocations.
+fn macro_invocations(json: &JsonValue) -> collections::HashMap<&str, (Vec<&str>, Vec<String>)> {
+    let mut hm = collections::HashMap::new();
+    for member in json["items"].members() {
+        if member["type"] != "macro" {
+            continue;
+        }
+        let path = member["name"].as_str().unwrap_or("<error>");
+        let entry = hm.entry(path).or_insert_with(|| (Vec::new(), Vec::new()));
+        entry.0.push(member["fingerprint"].as_str().unwrap_or(""));
+        entry
+            .1
+            .push(location(member).unwrap_or_else(|| "<unknown>".to_owned()));
+    }
+    for entry in hm.values_mut() {
+        entry.0.sort();
+    }
+    hm
+}
+
+/// Returns the feature sets used to extract both descriptions.
+fn common_feature_sets(json1: &JsonValue, json2: &JsonValue) -> Option<Vec<Vec<String>>> {
+    if json1["feature_sets"].is_null() || json2["feature_sets"].is_null() {
+        return None;
+    }
+    let sets2: Vec<_> = json2["feature_sets"].members().map(feature_set).collect();
+    let common = json1["feature_sets"]
+        .members()
+        .map(feature_set)
+        .filter(|s| sets2.contains(s))
+        .collect();
+    Some(common)
+}
+
+fn feature_set(json: &JsonValue) -> Vec<String> {
+    let mut v: Vec<String> = json
+        .members()
+        .filter_map(|f| f.as_str().map(|s| s.to_owned()))
+        .collect();
+    v.sort();
+    v
+}
+
+/// Collect, for each item name, the feature sets enabling the item.
+fn item_features(
+    json: &JsonValue,
+) -> collections::HashMap<String, collections::BTreeSet<Vec<String>>> {
+    let mut hm = collections::HashMap::new();
+    for member in json["items"].members() {
+        if member["features"].is_null() {
+            continue;
+        }
+        let sets = hm
+            .entry(item_key(member))
+            .or_insert_with(collections::BTreeSet::new);
+        for set in member["features"].members() {
+            sets.insert(feature_set(set));
+        }
+    }
+    hm
+}
+
+fn compare_features(
+    name: &str,
+    sets1: &collections::BTreeSet<Vec<String>>,
+    sets2: &collections::BTreeSet<Vec<String>>,
+    config: &Config,
+) -> bool {
+    let common = match config.feature_sets {
+        Some(ref common) => common,
+        None => return false,
+    };
+    // only compare feature sets used for both descriptions
+    let sets1: Vec<_> = sets1.iter().filter(|s| common.contains(s)).collect();
+    let sets2: Vec<_> = sets2.iter().filter(|s| common.contains(s)).collect();
+    let mut changed = false;
+    let required1 = required_features(&sets1);
+    let required2 = required_features(&sets2);
+    for f in required2.difference(&required1) {
+        info!("Item '{}' now requires feature `{}`", name, f);
+        changed = true;
+    }
+    for f in required1.difference(&required2) {
+        info!("Item '{}' no longer requires feature `{}`", name, f);
+        changed = true;
+    }
+    for set in sets1.iter().filter(|s| !sets2.contains(s)) {
+        info!(
+            "Item '{}' is no longer available with features [{}]",
+            name,
+            set.join(", ")
+        );
+        changed = true;
+    }
+    changed
+}
+
+/// Features enabled in every feature set where the item is available.
+fn required_features(sets: &[&Vec<String>]) -> collections::BTreeSet<String> {
+    let mut iter = sets.iter();
+    let mut required: collections::BTreeSet<String> = match iter.next() {
+        Some(first) => first.iter().cloned().collect(),
+        None => return collections::BTreeSet::new(),
+    };
+    for set in iter {
+        required.retain(|f| set.contains(f));
+    }
+    required
 }
 
 const TRAITS_KEYS: &'static [&'static str] = &[
     "type",
     "typarambounds",
-    "unsafety",
+    "unsafe",
     "generics",
     "where",
     "visibility",
@@ -279,7 +871,9 @@ fn compare_traits(
 const IMPL_KEYS: &'static [&'static str] = &[
     "type",
     "impl_type",
-    "unsafety",
+    "trait",
+    "unsafe",
+    "const",
     "generics",
     "where",
     "visibility",
@@ -300,10 +894,10 @@ fn compare_impl(
 
 const FN_KEYS: &'static [&'static str] = &[
     "type",
-    "output",
-    "abi",
-    "unsafety",
-    "constness",
+    "return_type",
+    "extern",
+    "unsafe",
+    "const",
     "generics",
     "where",
     "visibility",
@@ -312,8 +906,15 @@ const FN_KEYS: &'static [&'static str] = &[
     "attrs",
 ];
 
-const STRUCT_KEYS: &'static [&'static str] =
-    &["type", "generics", "where", "visibility", "fields", "attrs"];
+const STRUCT_KEYS: &'static [&'static str] = &[
+    "type",
+    "generics",
+    "where",
+    "visibility",
+    "repr",
+    "fields",
+    "attrs",
+];
 
 const CONST_KEYS: &'static [&'static str] = &["type", "subtype", "visibility", "attrs"];
 
@@ -327,13 +928,52 @@ fn compare_key(json1: &JsonValue, json2: &JsonValue, name: &str, index: &str) ->
     // debug!("\tjs1: {:?}", it1);
     // debug!("\tjs2: {:?}", it2);
     if index == "fields" {
-        return compare_fields(it1, it2, name);
+        let ty = json1["type"].as_str().unwrap_or("<error>");
+        return compare_fields(it1, it2, ty, name);
     }
     // if it1.is_null() || it2.is_null() { return true; }
-    it1 != it2
+    resolved_value(json1, index) != resolved_value(json2, index)
+}
+
+/// Returns the value of a key, where types are replaced by their canonical form (extracted
+/// with `--resolve-paths`) if it is known.
+///
+/// The written form of types is used for display.
+fn resolved_value(json: &JsonValue, key: &str) -> JsonValue {
+    let resolved = &json[format!("resolved_{}", key).as_str()];
+    if !resolved.is_null() {
+        return resolved.clone();
+    }
+    let value = &json[key];
+    match *value {
+        JsonValue::Array(ref members) => JsonValue::Array(
+            members
+                .iter()
+                .map(|member| {
+                    if member.is_object() {
+                        resolved_object(member)
+                    } else {
+                        member.clone()
+                    }
+                })
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Returns an object (a function argument, a field) with the canonical form of its types.
+fn resolved_object(json: &JsonValue) -> JsonValue {
+    let mut resolved = JsonValue::new_object();
+    for (key, _) in json.entries() {
+        if !key.starts_with("resolved_") {
+            resolved[key] = resolved_value(json, key);
+        }
+    }
+    resolved
 }
 
-fn compare_fields(json1: &JsonValue, json2: &JsonValue, name: &str) -> bool {
+fn compare_fields(json1: &JsonValue, json2: &JsonValue, ty1: &str, name: &str) -> bool {
     // XXX let f1 = &json1["fields"];
     // XXX let f2 = &json2["fields"];
     let f1 = json1;
@@ -345,7 +985,6 @@ fn compare_fields(json1: &JsonValue, json2: &JsonValue, name: &str) -> bool {
         warn!("malformed item '{}':\n\t{:?}\n\t{:?}", name, f1, f2);
         return true;
     }
-    let ty1 = json1["type"].as_str().unwrap_or("<error>");
     let mut h1 = collections::HashSet::new();
     let mut hm1 = collections::HashMap::new();
     for member in f1.members() {
@@ -372,11 +1011,23 @@ fn compare_fields(json1: &JsonValue, json2: &JsonValue, name: &str) -> bool {
     }
     let mut changed = false;
     for m in h1.difference(&h2) {
-        info!("{} '{}': removed field: '{}'", ty1, name, m);
+        info!(
+            "{} '{}': removed field: '{}'{}",
+            ty1,
+            name,
+            m,
+            location_suffix(hm1[m])
+        );
         changed = true;
     }
     for m in h2.difference(&h1) {
-        info!("{} '{}': added field: '{}'", ty1, name, m);
+        info!(
+            "{} '{}': added field: '{}'{}",
+            ty1,
+            name,
+            m,
+            location_suffix(hm2[m])
+        );
         changed = true;
     }
     for m in h1.intersection(&h2) {
@@ -389,7 +1040,7 @@ fn compare_fields(json1: &JsonValue, json2: &JsonValue, name: &str) -> bool {
     changed
 }
 
-const STRUCTFIELD_KEYS: &'static [&'static str] = &["type", "visibility", "fields"];
+const STRUCTFIELD_KEYS: &'static [&'static str] = &["type", "visibility", "discriminant", "fields"];
 fn compare_structfields(json1: &JsonValue, json2: &JsonValue, ty: &str, name: &str) -> bool {
     // debug!("compare_structfields {}:\n\t{:?}\n\t{:?}", name, json1, json2);
     if !json1.is_object() || !json2.is_object() {
@@ -409,8 +1060,14 @@ fn compare_structfields(json1: &JsonValue, json2: &JsonValue, ty: &str, name: &s
             let it1 = &json1[*key];
             let it2 = &json2[*key];
             info!(
-                "{} '{}': field '{}' has changed '{}' from '{}' to '{}'",
-                ty, name, fname, key, it1, it2
+                "{} '{}': field '{}' has changed '{}' from '{}' to '{}'{}",
+                ty,
+                name,
+                fname,
+                key,
+                it1,
+                it2,
+                locations_suffix(json1, json2)
             );
             return true;
         }
@@ -434,21 +1091,22 @@ fn compare_item_keys(json1: &JsonValue, json2: &JsonValue, keys: &[&str]) -> boo
         warn!("compare_item_keys: json value is not an object");
         return true;
     }
-    let fname = match &json1["name"] {
-        &JsonValue::Short(ref s) => s.as_str(),
-        &JsonValue::String(ref s) => s,
-        _e => {
-            warn!("json value has no 'name' attribute");
-            return true;
-        }
-    };
+    if json1["name"].as_str().is_none() {
+        warn!("json value has no 'name' attribute");
+        return true;
+    }
+    let fname = &item_key(json1);
     for key in keys {
         if compare_key(json1, json2, fname, key) {
             let it1 = &json1[*key];
             let it2 = &json2[*key];
             info!(
-                "Item '{}': property '{}' has changed from '{}' to '{}'",
-                fname, key, it1, it2
+                "Item '{}': property '{}' has changed from '{}' to '{}'{}",
+                fname,
+                key,
+                it1,
+                it2,
+                locations_suffix(json1, json2)
             );
             return true;
         }
@@ -456,14 +1114,91 @@ fn compare_item_keys(json1: &JsonValue, json2: &JsonValue, keys: &[&str]) -> boo
     return false;
 }
 
-const USETREE_KEYS: &'static [&'static str] = &["path", "kind", "visibility"];
+/// Returns the source location of an item as `file:line`, if known.
+fn location(json: &JsonValue) -> Option<String> {
+    let loc = &json["location"];
+    match (loc["file"].as_str(), loc["line"].as_usize()) {
+        (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+        _ => None,
+    }
+}
+
+/// Returns ` (file:line)` for an added or removed item, or an empty string.
+fn location_suffix(json: &JsonValue) -> String {
+    location(json)
+        .map(|l| format!(" ({})", l))
+        .unwrap_or_default()
+}
+
+/// Returns ` (old: file:line, new: file:line)` for a changed item, or an empty string.
+fn locations_suffix(json1: &JsonValue, json2: &JsonValue) -> String {
+    match (location(json1), location(json2)) {
+        (Some(l1), Some(l2)) => format!(" (old: {}, new: {})", l1, l2),
+        (Some(l1), None) => format!(" (old: {})", l1),
+        (None, Some(l2)) => format!(" (new: {})", l2),
+        (None, None) => String::new(),
+    }
+}
+
+const USETREE_KEYS: &'static [&'static str] = &["path", "kind", "usetree", "visibility"];
+
+const EXTERN_CRATE_KEYS: &'static [&'static str] = &["type", "crate", "visibility", "attrs"];
 
 fn show_report(report: &DiffReport) {
+    if !report.crates.is_empty() {
+        println!("Crates:");
+        for c in &report.crates {
+            show_crate_verdict(c);
+        }
+    }
     println!("Summary:");
+    if report.crates_added != 0 || report.crates_removed != 0 || !report.crates.is_empty() {
+        println!("    Crates added: {}", report.crates_added);
+        println!("    Crates removed: {}", report.crates_removed);
+    }
     println!("    Modules added: {}", report.mods_added);
     println!("    Modules removed: {}", report.mods_removed);
     println!("    Modules changed: {}", report.mods_changed);
     println!("    Items added: {}", report.items_added);
     println!("    Items removed: {}", report.items_removed);
     println!("    Items changed: {}", report.items_changed);
+    println!(
+        "    Macro invocations changed (unverifiable): {}",
+        report.macros_changed
+    );
+    println!("    ABI changes: {}", report.abi_changed);
+    println!("    New unsafe surface: {}", report.unsafe_added);
+    println!(
+        "    Public dependencies changed: {}",
+        report.public_deps_changed
+    );
+    println!(
+        "    Auto traits lost (heuristic): {}",
+        report.auto_traits_lost
+    );
+    if report.errors != 0 {
+        println!("    Errors: {}", report.errors);
+    }
+}
+
+fn show_crate_verdict(c: &CrateVerdict) {
+    let (old_version, new_version) = match (&c.old_version, &c.new_version) {
+        (Some(v1), Some(v2)) => (v1, v2),
+        _ => {
+            println!("    {}: {}", c.name, c.verdict);
+            return;
+        }
+    };
+    let bump = Verdict::from_versions(old_version, new_version);
+    let note = match bump {
+        Some(bump) if bump < c.verdict => format!(" (version bump is only {})", bump),
+        None if c.verdict > Verdict::Patch && old_version == new_version => {
+            " (version not increased)".to_owned()
+        }
+        _ => String::new(),
+    };
+    println!(
+        "    {} {} -> {}: {}{}",
+        c.name, old_version, new_version, c.verdict, note
+    );
 }
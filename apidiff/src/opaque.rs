@@ -0,0 +1,189 @@
+//! Opaque return types: `async fn`, and functions returning `impl Trait` or a boxed future.
+//!
+//! Callers only know the bounds promised by an opaque return type, so a bound which is no
+//! longer promised (like `Send`) breaks them even if the function still compiles. Return types
+//! are compared as callers see them: `async fn f() -> T` and
+//! `fn f() -> impl Future<Output = T>` are the same function.
+
+use json::JsonValue;
+
+use aliases::{split_top_level, value};
+
+/// Set the return type of the functions and methods of a description, or of each crate of a
+/// workspace description, as callers see it.
+///
+/// The return type of `async fn` is `impl Future<Output = T>`, and the bounds of opaque types
+/// are sorted. Like the canonical types extracted with `--resolve-paths`, the normalized type
+/// is stored in the `resolved_return_type` key.
+pub fn normalize_return_types(json: &mut JsonValue) {
+    if json.has_key("crates") {
+        for (_, krate) in json["crates"].entries_mut() {
+            normalize_return_types(krate);
+        }
+        return;
+    }
+    for module in json["modules"].members_mut() {
+        normalize_items(module);
+    }
+}
+
+fn normalize_items(json: &mut JsonValue) {
+    // indexing a missing key would insert it
+    if !json.has_key("items") {
+        return;
+    }
+    for item in json["items"].members_mut() {
+        match item["type"].as_str() {
+            Some("function") | Some("method") => normalize_function(item),
+            _ => normalize_items(item),
+        }
+    }
+}
+
+fn normalize_function(item: &mut JsonValue) {
+    if !item.has_key("inputs") {
+        return;
+    }
+    let normalized = match opaque_bounds(item) {
+        Some(bounds) => format!("impl {}", bounds.join(" + ")),
+        None => value(item, "output").unwrap_or("").to_owned(),
+    };
+    if !item.has_key("return_type") {
+        // the written form, for display
+        item["return_type"] = item["output"].clone();
+    }
+    if item["return_type"].as_str() != Some(normalized.as_str()) {
+        item["resolved_return_type"] = JsonValue::String(normalized);
+    }
+}
+
+/// Returns a description of each change of the opaque return type of a function.
+pub fn return_changes(json1: &JsonValue, json2: &JsonValue) -> Vec<String> {
+    let mut changes = Vec::new();
+    let output1 = json1["output"].as_str().unwrap_or("");
+    let output2 = json2["output"].as_str().unwrap_or("");
+    let bounds1 = opaque_bounds(json1);
+    // `async fn` written as a fn returning `impl Future`, or the reverse
+    let equivalent = bounds1.is_some() && bounds1 == opaque_bounds(json2);
+    match (json1["async"] == true, json2["async"] == true) {
+        _ if equivalent => (),
+        (true, false) if is_boxed_future(output2) => changes.push(format!(
+            "async fn changed to a fn returning a boxed future '{}'",
+            output2
+        )),
+        (true, false) => changes.push(format!("async fn changed to a fn returning '{}'", output2)),
+        (false, true) => changes.push(format!(
+            "fn returning '{}' changed to an async fn",
+            if output1.is_empty() { "()" } else { output1 }
+        )),
+        _ => (),
+    }
+    if let (Some(bounds1), Some(bounds2)) = (promised_bounds(json1), promised_bounds(json2)) {
+        for bound in bounds1.iter().filter(|b| !bounds2.contains(b)) {
+            changes.push(format!("return type no longer promises `{}`", bound));
+        }
+    }
+    changes
+}
+
+/// Returns the sorted bounds of an opaque return type, if the function returns one.
+///
+/// The bounds are read from `return_bounds`, or from the canonical form of the return type
+/// when it is known.
+fn opaque_bounds(json: &JsonValue) -> Option<Vec<String>> {
+    let output = value(json, "output").unwrap_or("");
+    let mut bounds = if json["async"] == true {
+        let output = if output.is_empty() { "()" } else { output };
+        vec![format!("Future<Output = {}>", output)]
+    } else if json.has_key("return_bounds") && !json.has_key("resolved_output") {
+        json["return_bounds"]
+            .members()
+            .filter_map(|b| b.as_str())
+            .map(unqualified_bound)
+            .collect()
+    } else if let Some(bounds) = output.strip_prefix("impl ") {
+        dyn_bounds(bounds)
+    } else {
+        return None;
+    };
+    bounds.sort();
+    Some(bounds)
+}
+
+/// Returns the bounds promised by an opaque return type, or by a boxed `dyn` future.
+fn promised_bounds(json: &JsonValue) -> Option<Vec<String>> {
+    if let Some(bounds) = opaque_bounds(json) {
+        return Some(bounds);
+    }
+    let output = json["output"].as_str().unwrap_or("");
+    if !is_boxed_future(output) {
+        return None;
+    }
+    if let Some(start) = output.find("dyn ") {
+        return Some(dyn_bounds(&output[start + 4..]));
+    }
+    // `futures::future::BoxFuture<'a, T>` and `LocalBoxFuture<'a, T>`
+    let start = output.find("BoxFuture<")? + "BoxFuture".len();
+    let args = &output[start + 1..];
+    let end = bounds_end(args);
+    let mut bounds = vec![format!(
+        "Future<Output = {}>",
+        split_top_level(&args[..end]).pop()?
+    )];
+    if !output.contains("LocalBoxFuture<") {
+        bounds.push("Send".to_owned());
+    }
+    Some(bounds)
+}
+
+/// `Pin<Box<dyn Future<Output = T> + Send>>` and `BoxFuture<'a, T>` are boxed futures.
+fn is_boxed_future(output: &str) -> bool {
+    output.contains("BoxFuture<") || (output.contains("Box<dyn ") && output.contains("Future<"))
+}
+
+/// Returns the bounds of a `dyn A + B<C>` type, starting after `dyn`.
+fn dyn_bounds(ty: &str) -> Vec<String> {
+    let ty = &ty[..bounds_end(ty)];
+    let mut bounds = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, c) in ty.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if ty[..idx].ends_with('-') => (),
+            '>' | ')' | ']' => depth -= 1,
+            '+' if depth == 0 => {
+                bounds.push(unqualified_bound(ty[start..idx].trim()));
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    bounds.push(unqualified_bound(ty[start..].trim()));
+    bounds
+}
+
+/// Returns the position of the end of a type or of a list of generic arguments, at the
+/// first unbalanced closing delimiter.
+fn bounds_end(ty: &str) -> usize {
+    let mut depth = 0;
+    for (idx, c) in ty.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if ty[..idx].ends_with('-') => (),
+            '>' | ')' | ']' if depth == 0 => return idx,
+            '>' | ')' | ']' => depth -= 1,
+            _ => (),
+        }
+    }
+    ty.len()
+}
+
+/// `std::future::Future<Output = T>` is promised by `Future<Output = T>`.
+fn unqualified_bound(bound: &str) -> String {
+    let path_end = bound.find('<').unwrap_or(bound.len());
+    match bound[..path_end].rfind("::") {
+        Some(idx) => bound[idx + 2..].to_owned(),
+        None => bound.to_owned(),
+    }
+}
@@ -0,0 +1,80 @@
+//! Semver verdicts: the version bump required by a set of API changes.
+
+use std::fmt;
+
+use DiffReport;
+
+/// Kind of version bump, ordered by severity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Verdict::Patch => "patch",
+            Verdict::Minor => "minor",
+            Verdict::Major => "major",
+        };
+        f.write_str(s)
+    }
+}
+
+impl Verdict {
+    /// Returns the version bump required by the changes of `report`.
+    ///
+    /// Removed and changed items are considered breaking, added items are not.
+    pub fn from_report(report: &DiffReport) -> Verdict {
+        if report.mods_removed != 0 || report.items_removed != 0 || report.items_changed != 0 {
+            Verdict::Major
+        } else if report.mods_added != 0 || report.items_added != 0 {
+            Verdict::Minor
+        } else {
+            Verdict::Patch
+        }
+    }
+
+    /// Returns the kind of bump between two versions, following cargo rules
+    /// (for `0.y.z` versions, a change of `y` is a major bump).
+    ///
+    /// Returns `None` if a version cannot be parsed or is not increased.
+    pub fn from_versions(old: &str, new: &str) -> Option<Verdict> {
+        let old = parse_version(old)?;
+        let new = parse_version(new)?;
+        if new <= old {
+            return None;
+        }
+        let verdict = if old.0 != new.0 {
+            Verdict::Major
+        } else if old.1 != new.1 {
+            if old.0 == 0 {
+                Verdict::Major
+            } else {
+                Verdict::Minor
+            }
+        } else if old.0 == 0 && old.1 == 0 {
+            Verdict::Major
+        } else if old.0 == 0 {
+            Verdict::Minor
+        } else {
+            Verdict::Patch
+        };
+        Some(verdict)
+    }
+}
+
+/// Parse `major.minor.patch`, ignoring pre-release and build metadata.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|p| p.trim().parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let patch = parts.next()?.ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
@@ -0,0 +1,43 @@
+//! Unsafe surface: `unsafe fn`, `unsafe trait` and `unsafe impl` items, `static mut` items,
+//! and raw pointers in public fields and signatures.
+//!
+//! Additions to the unsafe surface are reported separately from other changes, so that a
+//! release can be reviewed for the unsafe code it exposes.
+
+use apicheck_common::unsafety;
+use json::JsonValue;
+
+use item_key;
+
+/// Returns the descriptions of the unsafe parts of an item, without the items it contains.
+fn unsafe_parts(json: &JsonValue) -> Vec<String> {
+    unsafety::unsafe_parts(json)
+        .iter()
+        .map(|part| part.to_string())
+        .collect()
+}
+
+/// Returns the unsafe parts of a new item, including the items it contains.
+pub fn unsafe_surface(json: &JsonValue) -> Vec<String> {
+    let mut parts = unsafe_parts(json);
+    for item in json["items"].members() {
+        let key = item_key(item);
+        parts.extend(
+            unsafe_surface(item)
+                .into_iter()
+                .map(|part| format!("'{}': {}", key, part)),
+        );
+    }
+    parts
+}
+
+/// Returns the unsafe parts of the new version of an item which were not in the old version.
+///
+/// The items contained in both versions are compared separately.
+pub fn new_unsafe_surface(json1: &JsonValue, json2: &JsonValue) -> Vec<String> {
+    let parts1 = unsafe_parts(json1);
+    unsafe_parts(json2)
+        .into_iter()
+        .filter(|part| !parts1.contains(part))
+        .collect()
+}
@@ -0,0 +1,18 @@
+pub struct MyError {
+    pub code: u32,
+}
+
+pub type Result<T> = std::result::Result<T, MyError>;
+
+pub type TwoTuple<T>
+where
+    T: Sized,
+= (T, T);
+
+pub fn parse(s: &str) -> std::result::Result<u32, MyError> {
+    s.parse().map_err(|_| MyError { code: 1 })
+}
+
+pub fn pair(x: u8) -> (u8, u8) {
+    (x, x)
+}
@@ -0,0 +1,15 @@
+pub mod error {
+    pub struct MyError {
+        pub code: u32,
+    }
+
+    pub type Result<T> = ::std::result::Result<T, MyError>;
+}
+
+pub mod api {
+    use error::Result;
+
+    pub fn parse(s: &str) -> Result<u32> {
+        s.parse().map_err(|_| ::error::MyError { code: 1 })
+    }
+}
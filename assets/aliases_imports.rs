@@ -0,0 +1,13 @@
+use crate::error::Result;
+
+pub mod error {
+    pub struct MyError {
+        pub code: u32,
+    }
+
+    pub type Result<T> = std::result::Result<T, MyError>;
+}
+
+pub fn parse(s: &str) -> Result<u32> {
+    s.parse().map_err(|_| error::MyError { code: 1 })
+}
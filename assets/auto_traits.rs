@@ -0,0 +1,20 @@
+use std::cell::Cell;
+
+pub struct Counter {
+    count: Cell<u32>,
+}
+
+pub struct Handle {
+    name: String,
+    inner: Inner,
+}
+
+struct Inner {
+    data: Vec<u8>,
+}
+
+pub struct Raw {
+    ptr: *mut u8,
+}
+
+unsafe impl Send for Raw {}
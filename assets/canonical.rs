@@ -0,0 +1,24 @@
+// items are sorted by name, then by type
+#[must_use]
+#[inline]
+pub fn zeta(b: u32, a: u32) -> u32 { a + b }
+
+// named fields are sorted
+pub struct Point {
+    pub y: i32,
+    pub x: i32,
+}
+
+// tuple fields keep their order
+pub struct Pair(pub u64, pub u32);
+
+impl Point {
+    pub fn origin() -> Point { Point { x: 0, y: 0 } }
+}
+
+pub fn alpha() { }
+
+pub trait Shape {
+    fn perimeter(&self) -> f64;
+    fn area(&self) -> f64;
+}
@@ -0,0 +1,11 @@
+#[cfg(foo)]
+pub fn with_foo() {}
+
+#[cfg(not(foo))]
+pub fn without_foo() {}
+
+#[cfg(level = "2")]
+pub fn level_2() {}
+
+#[cfg(unix)]
+pub fn unix_only() {}
@@ -0,0 +1,11 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(unix)] {
+        pub fn platform() -> u32 { 1 }
+    } else if #[cfg(windows)] {
+        pub fn platform() -> u32 { 2 }
+    } else {
+        pub fn platform() -> u32 { 0 }
+    }
+}
@@ -0,0 +1,11 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(unix)] {
+        pub mod cfg_if_unix;
+    } else if #[cfg(windows)] {
+        pub mod cfg_if_windows;
+    } else {
+        pub mod cfg_if_other;
+    }
+}
@@ -0,0 +1,3 @@
+pub fn platform() -> u32 {
+    2
+}
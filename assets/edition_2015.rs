@@ -0,0 +1,4 @@
+// `async` and `await` are not keywords in the 2015 edition
+pub fn async(await: u32) -> u32 {
+    await
+}
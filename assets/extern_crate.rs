@@ -0,0 +1,6 @@
+// re-exported crates
+pub extern crate core;
+pub extern crate std as standard;
+
+// should not be seen, not exported
+extern crate alloc;
@@ -0,0 +1,7 @@
+pub fn always() {}
+
+#[cfg(feature = "alloc")]
+pub fn with_alloc() {}
+
+#[cfg(feature = "std")]
+pub fn with_std() {}
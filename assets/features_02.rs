@@ -0,0 +1,23 @@
+pub fn always() {}
+
+#[cfg(feature = "std")]
+pub fn read_file() {}
+
+#[cfg(feature = "alloc")]
+pub fn with_alloc() {}
+
+#[cfg(any(unix, windows))]
+pub fn hosted() {}
+
+#[cfg(not(any(unix, windows)))]
+pub fn bare_metal() {}
+
+#[cfg(feature = "std")]
+pub fn buffer_size() -> usize {
+    4096
+}
+
+#[cfg(not(feature = "std"))]
+pub fn buffer_size() -> u16 {
+    512
+}
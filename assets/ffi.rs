@@ -0,0 +1,55 @@
+use std::os::raw::{c_char, c_int, c_void};
+
+pub type Callback = Option<unsafe extern "C" fn(data: *mut c_void, len: usize) -> c_int>;
+
+// not used by the FFI surface
+pub type Handler = fn(u32) -> u32;
+
+#[repr(C)]
+pub struct Options {
+    pub name: *const c_char,
+    pub callback: Callback,
+    pub data: *mut c_void,
+    pub key: [u8; 16],
+}
+
+#[repr(C)]
+pub union Value {
+    pub int: i64,
+    pub float: f64,
+}
+
+#[repr(u32)]
+pub enum Status {
+    Ok = 0,
+    Error = 1,
+}
+
+#[no_mangle]
+pub static mut LAST_ERROR: c_int = 0;
+
+#[no_mangle]
+pub extern "C" fn lib_init(options: *const Options) -> Status {
+    Status::Ok
+}
+
+#[export_name = "lib_get_value"]
+pub unsafe extern "C" fn get_value(out: *mut Value) { }
+
+// not part of the FFI surface
+pub struct Context;
+
+pub fn helper(x: u32) -> u32 { x }
+
+impl Context {
+    pub extern "C" fn method(&self) { }
+}
+
+pub mod sys {
+    use std::os::raw::c_char;
+
+    #[no_mangle]
+    pub extern "C" fn lib_version() -> *const c_char {
+        std::ptr::null()
+    }
+}
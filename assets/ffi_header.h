@@ -0,0 +1,29 @@
+/* FFI surface, generated by apicheck */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef void (*Logger)(int level, const char *fmt, ...);
+
+typedef int (*Compare)(const void *, const void *);
+
+typedef int (*Register)(int32_t (*cb)(int32_t), void *data);
+
+typedef struct Buffer {
+    uint8_t *data;
+    char **argv;
+    char *const *env;
+    float matrix[4][4];
+    const char *names[8];
+    const uint8_t (*header)[4];
+    Logger logger;
+    void (*on_close)(Buffer *buffer);
+} Buffer;
+
+extern const char VERSION[8];
+
+int buffer_register(Buffer *buffer, Register callback);
+bool buffer_sort(Buffer *buffer, Compare compare);
+const char *(*buffer_handler(const Buffer *buffer))(Buffer *);
+int buffer_printf(Buffer *buffer, const char *fmt, ...);
@@ -0,0 +1,47 @@
+use std::os::raw::{c_char, c_int, c_void};
+
+// nullable variadic function pointer
+pub type Logger = Option<unsafe extern "C" fn(level: c_int, fmt: *const c_char, ...)>;
+
+pub type Compare = extern "C" fn(*const c_void, *const c_void) -> c_int;
+
+// callback returning a value, as an argument of a function pointer
+pub type Register = extern "C" fn(cb: Option<extern "C" fn(i32) -> i32>, data: *mut c_void) -> c_int;
+
+#[repr(C)]
+pub struct Buffer {
+    pub data: *mut u8,
+    pub argv: *mut *mut c_char,
+    pub env: *const *mut c_char,
+    pub matrix: [[f32; 4]; 4],
+    pub names: [*const c_char; 8],
+    pub header: *const [u8; 4],
+    pub logger: Logger,
+    pub on_close: Option<extern "C" fn(buffer: *mut Buffer)>,
+}
+
+#[no_mangle]
+pub static VERSION: [c_char; 8] = [0; 8];
+
+#[no_mangle]
+pub extern "C" fn buffer_register(buffer: *mut Buffer, callback: Register) -> c_int {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn buffer_sort(buffer: *mut Buffer, compare: Compare) -> bool {
+    false
+}
+
+// returns a function pointer
+#[no_mangle]
+pub extern "C" fn buffer_handler(
+    buffer: &Buffer,
+) -> Option<extern "C" fn(*mut Buffer) -> *const c_char> {
+    None
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_printf(buffer: *mut Buffer, fmt: *const c_char, args: ...) -> c_int {
+    0
+}
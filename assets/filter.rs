@@ -0,0 +1,13 @@
+pub fn stable() { }
+
+pub struct Stable;
+
+pub mod ffi {
+    pub fn ffi_call() { }
+
+    pub struct FfiStruct;
+}
+
+// should not be seen, excluded by path with the file submodule it declares
+#[path = "filter_experimental.rs"]
+pub mod experimental;
@@ -0,0 +1,4 @@
+pub fn unstable() { }
+
+#[path = "filter_experimental_nested.rs"]
+pub mod nested;
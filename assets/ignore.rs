@@ -0,0 +1,4 @@
+// ignore_generated.rs is in the ignore list of ignore.toml
+pub mod ignore_generated;
+
+pub fn kept() { }
@@ -0,0 +1,2 @@
+// should not be seen, the file is ignored
+pub fn generated() { }
@@ -0,0 +1,17 @@
+// ignored with --ignore crate::ignore_* (the file is not parsed) and crate::**::internal,
+// except crate::public::internal, re-included by a later !crate::public::internal
+pub mod ignore_generated;
+
+pub mod internal {
+    pub fn helper() {}
+}
+
+pub mod public {
+    pub fn api() {}
+
+    pub mod internal {
+        pub fn kept() {}
+    }
+}
+
+pub fn kept() {}
@@ -0,0 +1,25 @@
+pub struct Parser {
+    input: String,
+}
+
+impl Parser {
+    pub fn get(&self) -> &str {
+        &self.input
+    }
+
+    pub fn split(&self, sep: &str) -> &str {
+        self.input.split(sep).next().unwrap_or("")
+    }
+}
+
+pub fn first(s: &str) -> &str {
+    &s[..1]
+}
+
+pub fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+    if x.len() > y.len() {
+        x
+    } else {
+        y
+    }
+}
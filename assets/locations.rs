@@ -0,0 +1,9 @@
+pub struct Point {
+    pub x: i32,
+    y: i32,
+}
+
+pub enum Shape {
+    Circle(f64),
+    Square(f64, f64),
+}
@@ -0,0 +1,3 @@
+register!(Alpha, Beta);
+
+pub fn init() {}
@@ -0,0 +1,3 @@
+pub fn init() {}
+
+register!(Alpha, Beta, Gamma);
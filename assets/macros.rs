@@ -0,0 +1,24 @@
+macro_rules! newtype {
+    ($name:ident, $inner:ty) => {
+        pub struct $name(pub $inner);
+    };
+}
+
+macro_rules! impl_zero {
+    ($($t:ident),*) => {
+        $(
+            impl Zero for $t {
+                fn zero() -> Self { $t(0) }
+            }
+        )*
+    };
+}
+
+pub trait Zero {
+    fn zero() -> Self;
+}
+
+newtype!(Meters, u64);
+newtype!(Seconds, u32);
+
+impl_zero!(Meters, Seconds);
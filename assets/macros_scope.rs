@@ -0,0 +1,32 @@
+#[macro_use]
+mod defs {
+    macro_rules! unit {
+        ($name:ident) => {
+            pub struct $name;
+        };
+    }
+}
+
+#[macro_export]
+macro_rules! constant {
+    ($name:ident, $value:expr) => {
+        pub const $name: u32 = $value;
+    };
+}
+
+unit!(First);
+
+// shadows the macro of `defs` for the following items
+macro_rules! unit {
+    ($name:ident) => {
+        pub struct $name(pub u8);
+    };
+}
+
+unit!(Second);
+
+pub mod inner {
+    crate::constant!(ANSWER, 42);
+
+    unit!(Third);
+}
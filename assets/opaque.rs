@@ -0,0 +1,13 @@
+use std::future::Future;
+
+pub async fn fetch(id: u32) -> String {
+    id.to_string()
+}
+
+pub fn numbers() -> impl Iterator<Item = u32> + Send {
+    0..10
+}
+
+pub fn task() -> impl Future<Output = ()> + Send {
+    async {}
+}
@@ -0,0 +1,2 @@
+// `try` is a reserved keyword since the 2018 edition
+pub fn try(_attempts: u32) {}
@@ -0,0 +1,27 @@
+use bytes::Bytes;
+use http::{HeaderMap, Uri as HttpUri};
+use std::fmt;
+
+mod model {
+    pub struct Model;
+}
+
+use model::Model;
+
+pub struct Frame {
+    pub headers: HeaderMap,
+    // private fields do not expose their types
+    payload: bytes::BytesMut,
+}
+
+pub fn decode(data: Bytes) -> Result<Frame, fmt::Error> {
+    unimplemented!()
+}
+
+pub fn parse_uri(s: &str) -> Option<HttpUri> {
+    None
+}
+
+pub fn local(m: Model) -> Vec<u8> {
+    Vec::new()
+}
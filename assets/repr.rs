@@ -0,0 +1,16 @@
+#[repr(C)]
+pub struct Header {
+    pub len: u32,
+    pub flags: u16,
+}
+
+#[repr(u8)]
+pub enum Kind {
+    A = 1,
+    B,
+}
+
+#[no_mangle]
+pub extern "C" fn header_len(h: *const Header) -> u32 {
+    unsafe { (*h).len }
+}
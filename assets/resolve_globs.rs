@@ -0,0 +1,14 @@
+pub mod error {
+    pub struct Error {
+        pub code: u32,
+    }
+}
+
+pub mod api {
+    use super::*;
+
+    // `error` is the sibling module, not a crate
+    pub fn parse(s: &str) -> Result<u32, error::Error> {
+        Err(error::Error { code: 1 })
+    }
+}
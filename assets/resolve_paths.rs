@@ -0,0 +1,14 @@
+mod model {
+    pub struct Foo;
+}
+
+use model::Foo;
+use std::collections::HashMap;
+
+pub fn list() -> Vec<Foo> {
+    Vec::new()
+}
+
+pub fn index(map: HashMap<u32, Foo>) -> Option<&'static Foo> {
+    None
+}
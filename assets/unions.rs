@@ -0,0 +1,9 @@
+pub union IntOrFloat {
+    pub i: u32,
+    pub f: f32,
+}
+
+// should not be seen, not exported
+union Private {
+    a: u8,
+}
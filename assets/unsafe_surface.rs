@@ -0,0 +1,44 @@
+pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> Vec<u8> {
+    Vec::from_raw_parts(ptr, len, len)
+}
+
+// safe, but takes a raw pointer
+pub fn is_null(ptr: *const u8) -> bool {
+    ptr.is_null()
+}
+
+pub fn safe() { }
+
+pub static mut COUNTER: u32 = 0;
+
+pub static NAME: &str = "name";
+
+pub struct Buffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+pub unsafe trait Zeroable {
+    fn zeroed() -> Self;
+}
+
+pub trait Storage {
+    fn get(&self) -> u32;
+    unsafe fn get_unchecked(&self, idx: usize) -> u32;
+}
+
+unsafe impl Zeroable for Buffer {
+    fn zeroed() -> Buffer {
+        Buffer { data: std::ptr::null_mut(), len: 0 }
+    }
+}
+
+impl Buffer {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.data
+    }
+}
@@ -1,4 +1,6 @@
 use rustc_ast::ast;
+use rustc_ast_pretty::pprust;
+use rustc_span::symbol::sym;
 use rustc_span::Symbol;
 
 pub(crate) fn first_attr_value_str_by_name(
@@ -11,6 +13,17 @@ pub(crate) fn first_attr_value_str_by_name(
         .and_then(|attr| attr.value_str())
 }
 
+/// Returns the hints of the `#[repr(...)]` attributes, like `C`, `u8` or `align(8)`.
+pub(crate) fn repr_of_attrs(attrs: &[ast::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.has_name(sym::repr))
+        .filter_map(|attr| attr.meta_item_list())
+        .flatten()
+        .filter_map(|nested| nested.meta_item().map(pprust::meta_item_to_string))
+        .collect()
+}
+
 pub(crate) trait MetaVisitor<'ast> {
     fn visit_meta_item(&mut self, meta_item: &'ast ast::MetaItem) {
         match meta_item.kind {
@@ -0,0 +1,413 @@
+//! Inference of the auto traits (`Send`, `Sync`, `Unpin`) of the types of the crate.
+//!
+//! This is a best-effort, structural analysis of the field types, using the well-known types
+//! of `std` which do not implement an auto trait (`Rc`, `Cell`, raw pointers, ...), the
+//! explicit `unsafe impl Send` and negative impls of the crate, and the fields of the types of
+//! the crate. Generic parameters and external types are assumed to implement the auto traits.
+//! The inferred auto traits are stored in the `auto_traits` key of structs, enums and unions.
+
+use json::JsonValue;
+use std::collections::{HashMap, HashSet};
+
+const AUTO_TRAITS: &[&str] = &["Send", "Sync", "Unpin"];
+
+/// A type, parsed from its written form.
+enum Ty {
+    /// `&T` and `&mut T`
+    Ref(bool, Box<Ty>),
+    /// `*const T` and `*mut T`
+    Ptr,
+    /// Tuples, arrays and slices
+    Seq(Vec<Ty>),
+    /// Trait objects, with the names of their bounds
+    Dyn(Vec<String>),
+    /// Paths, with their last segment and their type arguments
+    Path(String, String, Vec<Ty>),
+    /// Function pointers, primitive types and other types implementing all the auto traits
+    Other,
+}
+
+/// Field types and explicit impls of the auto traits, by type name.
+#[derive(Default)]
+struct Types {
+    fields: HashMap<String, Vec<String>>,
+    /// `true` for `unsafe impl Send for T`, `false` for `impl !Send for T`
+    impls: HashMap<(String, String), bool>,
+}
+
+/// Add the inferred auto traits of the structs, enums and unions of a description.
+pub fn auto_traits(json: &mut JsonValue) {
+    let mut types = Types::default();
+    for module in json["modules"].members() {
+        types.collect(module);
+    }
+    for module in json["modules"].members_mut() {
+        annotate(module, &types);
+    }
+}
+
+fn annotate(module: &mut JsonValue, types: &Types) {
+    for item in module["items"].members_mut() {
+        match item["type"].as_str() {
+            Some("mod") => annotate(item, types),
+            Some("struct") | Some("enum") | Some("union") => {
+                let name = name(item).to_owned();
+                let traits: Vec<JsonValue> = AUTO_TRAITS
+                    .iter()
+                    .filter(|t| types.infer(&name, t, &mut HashSet::new()))
+                    .map(|t| JsonValue::from(*t))
+                    .collect();
+                item["auto_traits"] = JsonValue::Array(traits);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Types {
+    fn collect(&mut self, module: &JsonValue) {
+        // the scope contains the private types
+        for (name, fields) in module["scope"]["types"].entries() {
+            let fields = fields
+                .members()
+                .filter_map(|f| f.as_str())
+                .map(String::from);
+            self.fields
+                .entry(name.to_owned())
+                .or_default()
+                .extend(fields);
+        }
+        for item in module["items"].members() {
+            match item["type"].as_str() {
+                Some("mod") => self.collect(item),
+                Some("struct") | Some("union") if !self.fields.contains_key(name(item)) => {
+                    let fields = item["fields"].members().filter_map(|f| f["type"].as_str());
+                    self.fields
+                        .insert(name(item).to_owned(), fields.map(String::from).collect());
+                }
+                Some("enum") if !self.fields.contains_key(name(item)) => {
+                    let fields = item["fields"]
+                        .members()
+                        .flat_map(|variant| variant["fields"].members())
+                        .filter_map(|f| f["type"].as_str());
+                    self.fields
+                        .insert(name(item).to_owned(), fields.map(String::from).collect());
+                }
+                Some("impl") => {
+                    let tr = item["trait"].as_str().unwrap_or("");
+                    let (positive, tr) = match tr.strip_prefix('!') {
+                        Some(tr) => (false, tr),
+                        None => (true, tr),
+                    };
+                    let tr = tr.rsplit("::").next().unwrap_or("");
+                    if !AUTO_TRAITS.contains(&tr) {
+                        continue;
+                    }
+                    if let Ty::Path(_, name, _) = parse(item["impl_type"].as_str().unwrap_or("")) {
+                        self.impls.insert((name, tr.to_owned()), positive);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Returns `true` if the type of the crate `name` implements the auto trait `tr`.
+    fn infer(&self, name: &str, tr: &str, visiting: &mut HashSet<String>) -> bool {
+        if let Some(&positive) = self.impls.get(&(name.to_owned(), tr.to_owned())) {
+            return positive;
+        }
+        let fields = match self.fields.get(name) {
+            Some(fields) => fields,
+            None => return true,
+        };
+        // recursive types implement the auto traits of their other fields
+        if !visiting.insert(name.to_owned()) {
+            return true;
+        }
+        let result = fields
+            .iter()
+            .all(|ty| self.has_trait(&parse(ty), tr, visiting));
+        visiting.remove(name);
+        result
+    }
+
+    fn has_trait(&self, ty: &Ty, tr: &str, visiting: &mut HashSet<String>) -> bool {
+        match *ty {
+            Ty::Ref(_, _) if tr == "Unpin" => true,
+            // `&T` is `Send` if `T` is `Sync`
+            Ty::Ref(false, ref inner) => self.has_trait(inner, "Sync", visiting),
+            Ty::Ref(true, ref inner) => self.has_trait(inner, tr, visiting),
+            Ty::Ptr => tr == "Unpin",
+            Ty::Seq(ref types) => types.iter().all(|t| self.has_trait(t, tr, visiting)),
+            Ty::Dyn(ref bounds) => bounds.iter().any(|b| b == tr),
+            Ty::Path(ref path, ref name, ref args) => {
+                self.path_has_trait(path, name, args, tr, visiting)
+            }
+            Ty::Other => true,
+        }
+    }
+
+    fn path_has_trait(
+        &self,
+        path: &str,
+        name: &str,
+        args: &[Ty],
+        tr: &str,
+        visiting: &mut HashSet<String>,
+    ) -> bool {
+        let is_std = ["std::", "core::", "alloc::"]
+            .iter()
+            .any(|p| path.starts_with(p));
+        if !is_std && self.fields.contains_key(name) {
+            return self.infer(name, tr, visiting);
+        }
+        let all_args = |tr: &str, visiting: &mut HashSet<String>| {
+            args.iter().all(|t| self.has_trait(t, tr, visiting))
+        };
+        match (name, tr) {
+            ("PhantomPinned", "Unpin") => false,
+            // pointers to pinned data are `Unpin`
+            ("Box", "Unpin") | ("Rc", "Unpin") | ("Arc", "Unpin") | ("Pin", "Unpin") => true,
+            ("NonNull", "Unpin") => true,
+            ("Rc", _) | ("NonNull", _) => false,
+            ("Weak", "Send") | ("Weak", "Sync") if path.contains("rc::") => false,
+            ("Cell", "Sync") | ("RefCell", "Sync") | ("UnsafeCell", "Sync") => false,
+            ("OnceCell", "Sync") if !path.contains("sync::") => false,
+            // `std::sync::mpsc`
+            ("Receiver", "Sync") | ("Sender", "Sync") => false,
+            ("MutexGuard", "Send") | ("RwLockReadGuard", "Send") | ("RwLockWriteGuard", "Send") => {
+                false
+            }
+            ("Arc", _) => all_args("Send", visiting) && all_args("Sync", visiting),
+            ("Mutex", _) => all_args("Send", visiting),
+            ("RwLock", "Sync") => all_args("Send", visiting) && all_args("Sync", visiting),
+            _ => all_args(tr, visiting),
+        }
+    }
+}
+
+fn name(item: &JsonValue) -> &str {
+    item["name"].as_str().unwrap_or("")
+}
+
+/// Parse a type, as printed in the description.
+fn parse(ty: &str) -> Ty {
+    let mut parser = Parser {
+        chars: ty.chars().collect(),
+        pos: 0,
+    };
+    parser.parse_type()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        self.skip_whitespace();
+        let end = self.pos + s.chars().count();
+        if end <= self.chars.len() && self.chars[self.pos..end].iter().cloned().eq(s.chars()) {
+            self.pos = end;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn lifetime(&mut self) {
+        if self.eat("'") {
+            self.ident();
+        }
+    }
+
+    /// Skip a balanced group, starting at its opening delimiter.
+    fn skip_group(&mut self) {
+        let mut depth = 0;
+        let mut prev = ' ';
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '<' | '(' | '[' => depth += 1,
+                '>' if prev == '-' => (),
+                '>' | ')' | ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => (),
+            }
+            prev = c;
+        }
+    }
+
+    /// Skip the rest of a type, up to the next unbalanced delimiter or comma.
+    fn skip_type(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                '<' | '(' | '[' => self.skip_group(),
+                '-' if self.chars.get(self.pos + 1) == Some(&'>') => self.pos += 2,
+                '>' | ')' | ']' | ',' | ';' | '=' => return,
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn parse_type(&mut self) -> Ty {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('&') => {
+                self.pos += 1;
+                self.lifetime();
+                let mutable = self.eat("mut ");
+                Ty::Ref(mutable, Box::new(self.parse_type()))
+            }
+            Some('*') => {
+                self.pos += 1;
+                self.skip_type();
+                Ty::Ptr
+            }
+            Some('(') => {
+                self.pos += 1;
+                let types = self.parse_list(')');
+                Ty::Seq(types)
+            }
+            Some('[') => {
+                self.pos += 1;
+                let ty = self.parse_type();
+                // array length
+                self.skip_type();
+                self.eat("]");
+                Ty::Seq(vec![ty])
+            }
+            Some(c) if c.is_alphabetic() || c == '_' || c == ':' => {
+                let start = self.pos;
+                let word = self.ident();
+                match word.as_str() {
+                    "dyn" | "impl" => Ty::Dyn(self.parse_bounds()),
+                    "fn" | "unsafe" | "extern" | "for" => {
+                        self.skip_type();
+                        Ty::Other
+                    }
+                    _ => {
+                        self.pos = start;
+                        self.parse_path()
+                    }
+                }
+            }
+            _ => {
+                // `!`, `<T as Trait>::Output`, `_`
+                self.skip_type();
+                Ty::Other
+            }
+        }
+    }
+
+    /// Parse the types of a list, up to the closing delimiter `end`.
+    fn parse_list(&mut self, end: char) -> Vec<Ty> {
+        let mut types = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some(c) if c == end => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(',') => self.pos += 1,
+                Some('\'') => self.lifetime(),
+                _ => {
+                    let start = self.pos;
+                    let ty = self.parse_type();
+                    if self.eat("=") {
+                        // associated type binding
+                        types.push(self.parse_type());
+                    } else {
+                        types.push(ty);
+                    }
+                    if self.pos == start {
+                        self.pos += 1;
+                    }
+                }
+            }
+        }
+        types
+    }
+
+    fn parse_path(&mut self) -> Ty {
+        let mut path = String::new();
+        let mut name;
+        let mut args = Vec::new();
+        loop {
+            if self.eat("::") {
+                path.push_str("::");
+            }
+            name = self.ident();
+            path.push_str(&name);
+            match self.peek() {
+                Some('<') => {
+                    self.pos += 1;
+                    args = self.parse_list('>');
+                }
+                // `Fn(A) -> B`
+                Some('(') => {
+                    self.skip_group();
+                    if self.eat("->") {
+                        self.parse_type();
+                    }
+                }
+                _ => (),
+            }
+            let pos = self.pos;
+            if !self.eat("::") {
+                break;
+            }
+            self.pos = pos;
+        }
+        Ty::Path(path, name, args)
+    }
+
+    /// Parse the bounds of a trait object, and return the names of the traits.
+    fn parse_bounds(&mut self) -> Vec<String> {
+        let mut bounds = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('\'') => self.lifetime(),
+                Some('?') => self.pos += 1,
+                Some(c) if c.is_alphabetic() || c == '_' || c == ':' => {
+                    if let Ty::Path(_, name, _) = self.parse_path() {
+                        bounds.push(name);
+                    }
+                }
+                _ => (),
+            }
+            if !self.eat("+") {
+                break;
+            }
+        }
+        bounds
+    }
+}
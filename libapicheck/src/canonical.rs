@@ -0,0 +1,102 @@
+//! Canonical output: a description which does not depend on the order of the code.
+//!
+//! Modules, items, trait and impl items, named struct fields and attributes are sorted by
+//! stable keys. Lists where the order is significant (arguments, tuple fields, enum
+//! variants, generic parameters) are left alone. Source locations are removed, since they
+//! change whenever code is moved.
+
+use std::cmp::Ordering;
+
+use json::JsonValue;
+
+/// Sort an API description in place.
+pub fn canonicalize(json: &mut JsonValue) {
+    if json.has_key("crates") {
+        for (_, krate) in json["crates"].entries_mut() {
+            canonicalize(krate);
+        }
+        return;
+    }
+    for module in json["modules"].members_mut() {
+        canonicalize_items(&mut module["items"]);
+    }
+    sort_array(&mut json["modules"], |a, b| {
+        module_key(a)
+            .cmp(&module_key(b))
+            .then_with(|| a.dump().cmp(&b.dump()))
+    });
+}
+
+fn module_key(module: &JsonValue) -> (&str, &str) {
+    (
+        module["module"].as_str().unwrap_or(""),
+        module["path"].as_str().unwrap_or(""),
+    )
+}
+
+/// Sort a list of items, after sorting the contents of each item.
+fn canonicalize_items(items: &mut JsonValue) {
+    for item in items.members_mut() {
+        canonicalize_item(item);
+    }
+    sort_array(items, |a, b| {
+        item_key(a)
+            .cmp(&item_key(b))
+            .then_with(|| a.dump().cmp(&b.dump()))
+    });
+}
+
+fn canonicalize_item(item: &mut JsonValue) {
+    remove_locations(item);
+    if item["items"].is_array() {
+        // modules, traits and impls
+        canonicalize_items(&mut item["items"]);
+    }
+    // the order of the fields of `#[repr]` types is part of the ABI
+    if item["type"] == "struct" && !item.has_key("repr") && has_named_fields(item) {
+        sort_array(&mut item["fields"], |a, b| {
+            a["name"].as_str().cmp(&b["name"].as_str())
+        });
+    }
+    if item.has_key("attrs") {
+        sort_array(&mut item["attrs"], |a, b| a.as_str().cmp(&b.as_str()));
+    }
+}
+
+/// Items are sorted by name (or implemented type, for impls) then by type, so that a type
+/// and its impls are next to each other.
+fn item_key(item: &JsonValue) -> (&str, &str) {
+    let name = match item["name"].as_str() {
+        Some(name) if !name.is_empty() => name,
+        _ => item["impl_type"].as_str().unwrap_or(""),
+    };
+    (name, item["type"].as_str().unwrap_or(""))
+}
+
+fn has_named_fields(item: &JsonValue) -> bool {
+    item["fields"]
+        .members()
+        .all(|field| field["name"] != "<anon>")
+}
+
+fn remove_locations(js: &mut JsonValue) {
+    if js.is_object() {
+        js.remove("location");
+        for (_, value) in js.entries_mut() {
+            remove_locations(value);
+        }
+    } else {
+        for member in js.members_mut() {
+            remove_locations(member);
+        }
+    }
+}
+
+fn sort_array<F>(js: &mut JsonValue, compare: F)
+where
+    F: Fn(&JsonValue, &JsonValue) -> Ordering,
+{
+    if let JsonValue::Array(ref mut v) = *js {
+        v.sort_by(compare);
+    }
+}
@@ -0,0 +1,212 @@
+//! Evaluation of `#[cfg(...)]` predicates.
+//!
+//! When a `CfgSet` is given in the configuration, items whose `cfg` attributes
+//! evaluate to false are not part of the extracted API.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt;
+use std::process::Command;
+
+use rustc_ast::ast;
+use rustc_ast_pretty::pprust;
+use rustc_span::symbol::sym;
+
+use crate::config::Config;
+
+/// A set of enabled cfg options, like `unix` or `feature = "std"`.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct CfgSet {
+    options: BTreeSet<(String, Option<String>)>,
+}
+
+impl CfgSet {
+    pub fn new() -> CfgSet {
+        CfgSet::default()
+    }
+
+    /// The cfg options of the host, as printed by `rustc --print cfg` (`unix`,
+    /// `target_os = "linux"`, ...).
+    ///
+    /// If `rustc` cannot be run, the options of the target apicheck was built for are used.
+    pub fn host() -> CfgSet {
+        let mut cfg = CfgSet::new();
+        let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        match Command::new(rustc).args(&["--print", "cfg"]).output() {
+            Ok(ref output) if output.status.success() => {
+                for line in String::from_utf8_lossy(&output.stdout).lines() {
+                    cfg.insert_str(line);
+                }
+            }
+            _ => {
+                let family = env::consts::FAMILY;
+                if family == "unix" || family == "windows" {
+                    cfg.insert(family, None);
+                }
+                cfg.insert("target_family", Some(family));
+                cfg.insert("target_os", Some(env::consts::OS));
+                cfg.insert("target_arch", Some(env::consts::ARCH));
+                let width = if cfg!(target_pointer_width = "64") {
+                    "64"
+                } else {
+                    "32"
+                };
+                cfg.insert("target_pointer_width", Some(width));
+                let endian = if cfg!(target_endian = "big") {
+                    "big"
+                } else {
+                    "little"
+                };
+                cfg.insert("target_endian", Some(endian));
+            }
+        }
+        cfg
+    }
+
+    pub fn insert(&mut self, name: &str, value: Option<&str>) {
+        self.options
+            .insert((name.to_owned(), value.map(|v| v.to_owned())));
+    }
+
+    pub fn insert_feature(&mut self, feature: &str) {
+        self.insert("feature", Some(feature));
+    }
+
+    /// Parse an option given as `name` or `name="value"` (the `rustc --cfg` syntax).
+    pub fn insert_str(&mut self, s: &str) {
+        match s.find('=') {
+            Some(idx) => {
+                let name = s[..idx].trim();
+                let value = s[idx + 1..].trim().trim_matches('"');
+                self.insert(name, Some(value));
+            }
+            None => self.insert(s.trim(), None),
+        }
+    }
+
+    pub fn contains(&self, name: &str, value: Option<&str>) -> bool {
+        self.options
+            .contains(&(name.to_owned(), value.map(|v| v.to_owned())))
+    }
+
+    /// Enabled features, in sorted order.
+    pub fn features(&self) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(name, _)| name == "feature")
+            .filter_map(|(_, value)| value.as_ref().map(|v| v.as_str()))
+            .collect()
+    }
+}
+
+/// A `cfg` predicate.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CfgExpr {
+    /// `name` or `name = "value"`
+    Name(String, Option<String>),
+    /// `all(..)`
+    All(Vec<CfgExpr>),
+    /// `any(..)`
+    Any(Vec<CfgExpr>),
+    /// `not(..)`
+    Not(Box<CfgExpr>),
+}
+
+impl CfgExpr {
+    /// Build a predicate from the content of a `cfg(..)` attribute.
+    ///
+    /// Returns `None` if the predicate is malformed.
+    pub(crate) fn from_meta_item(meta: &ast::MetaItem) -> Option<CfgExpr> {
+        let name = pprust::path_to_string(&meta.path);
+        match meta.kind {
+            ast::MetaItemKind::Word => Some(CfgExpr::Name(name, None)),
+            ast::MetaItemKind::NameValue(ref lit) => match lit.kind {
+                ast::LitKind::Str(value, _) => Some(CfgExpr::Name(name, Some(value.to_string()))),
+                _ => None,
+            },
+            ast::MetaItemKind::List(ref list) => {
+                let exprs = list
+                    .iter()
+                    .map(|nested| nested.meta_item().and_then(CfgExpr::from_meta_item))
+                    .collect::<Option<Vec<_>>>()?;
+                match name.as_str() {
+                    "all" => Some(CfgExpr::All(exprs)),
+                    "any" => Some(CfgExpr::Any(exprs)),
+                    "not" if exprs.len() == 1 => {
+                        Some(CfgExpr::Not(Box::new(exprs.into_iter().next().unwrap())))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Combine two optional conditions using `all(..)`.
+    pub(crate) fn and(a: Option<CfgExpr>, b: Option<CfgExpr>) -> Option<CfgExpr> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(CfgExpr::All(vec![a, b])),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    pub fn eval(&self, cfg: &CfgSet) -> bool {
+        match self {
+            CfgExpr::Name(name, value) => cfg.contains(name, value.as_ref().map(|v| v.as_str())),
+            CfgExpr::All(exprs) => exprs.iter().all(|e| e.eval(cfg)),
+            CfgExpr::Any(exprs) => exprs.iter().any(|e| e.eval(cfg)),
+            CfgExpr::Not(expr) => !expr.eval(cfg),
+        }
+    }
+}
+
+impl fmt::Display for CfgExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_list(f: &mut fmt::Formatter, name: &str, exprs: &[CfgExpr]) -> fmt::Result {
+            write!(f, "{}(", name)?;
+            for (i, e) in exprs.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", e)?;
+            }
+            write!(f, ")")
+        }
+        match self {
+            CfgExpr::Name(name, None) => write!(f, "{}", name),
+            CfgExpr::Name(name, Some(value)) => write!(f, "{} = \"{}\"", name, value),
+            CfgExpr::All(exprs) => write_list(f, "all", exprs),
+            CfgExpr::Any(exprs) => write_list(f, "any", exprs),
+            CfgExpr::Not(expr) => write!(f, "not({})", expr),
+        }
+    }
+}
+
+/// Returns the predicate of the `#[cfg(..)]` attributes, if any.
+///
+/// Several `cfg` attributes are combined using `all(..)`.
+pub(crate) fn cfg_of_attrs(attrs: &[ast::Attribute]) -> Option<CfgExpr> {
+    let mut exprs: Vec<_> = attrs
+        .iter()
+        .filter(|attr| attr.has_name(sym::cfg))
+        .filter_map(|attr| attr.meta_item_list())
+        .filter_map(|list| {
+            list.first()
+                .and_then(|nested| nested.meta_item())
+                .and_then(CfgExpr::from_meta_item)
+        })
+        .collect();
+    match exprs.len() {
+        0 => None,
+        1 => exprs.pop(),
+        _ => Some(CfgExpr::All(exprs)),
+    }
+}
+
+/// Returns `false` if cfg evaluation is enabled and the attributes disable the item.
+pub(crate) fn is_cfg_enabled(attrs: &[ast::Attribute], config: &Config) -> bool {
+    match (&config.cfg, cfg_of_attrs(attrs)) {
+        (Some(cfg), Some(expr)) => expr.eval(cfg),
+        _ => true,
+    }
+}
@@ -1,9 +1,20 @@
+use crate::cfg::CfgSet;
+use crate::filter::ItemFilter;
+use crate::manifest::Manifest;
+use crate::ErrorKind;
 use rustc_span::source_map::edition::Edition;
-use std::collections::{hash_set, HashSet};
 use std::default::Default;
+use std::env;
 use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::slice;
+use std::str::FromStr;
 
+/// Names of the configuration file, looked up in the directory of the input and its parents.
+pub const CONFIG_FILE_NAMES: &[&str] = &["apicheck.toml", ".apicheck.toml"];
+
+#[derive(Clone)]
 pub struct Config {
     pub debug: usize,
 
@@ -11,15 +22,70 @@ pub struct Config {
 
     pub output: FileName,
 
+    pub output_format: OutputFormat,
+
+    /// Sort the output, so that it does not depend on the order of the code.
+    pub canonical: bool,
+
+    /// Keep only the FFI surface: exported functions and statics, and `#[repr(C)]` types.
+    pub ffi: bool,
+
+    /// Keep only the unsafe surface: unsafe items, `static mut` and raw pointers.
+    pub unsafe_surface: bool,
+
+    /// List the dependencies exposed in the API, in the `public_dependencies` key.
+    pub public_dependencies: bool,
+
+    /// Add the canonical form of types, with fully qualified paths, in `resolved_` keys.
+    pub resolve_paths: bool,
+
+    /// Infer the auto traits (`Send`, `Sync`, `Unpin`) of the types, in `auto_traits` keys.
+    pub auto_traits: bool,
+
+    /// If set, items disabled by their `cfg` attributes are ignored.
+    pub cfg: Option<CfgSet>,
+
+    /// If set, extract the API once per feature set and merge the results.
+    pub feature_sets: Option<Vec<Vec<String>>>,
+
+    /// Extract the API for every combination of the features declared in the manifest.
+    pub feature_matrix: bool,
+
+    /// Expand invocations of the `macro_rules!` macros defined in the crate.
+    pub expand_macros: bool,
+
+    /// Selection of the items by path and kind.
+    pub filter: ItemFilter,
+
     hide_parse_errors: bool,
+
+    /// The edition was given explicitly, and overrides the edition of the manifests.
+    edition_set: bool,
+
+    /// The edition was read from a manifest.
+    edition_from_manifest: bool,
+
+    ignore: IgnoreList,
 }
 
-/// A set of directories, files and modules that rustfmt should ignore.
+/// Format of the JSON output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Single-line, compact JSON
+    Compact,
+    /// Indented JSON
+    Pretty,
+    /// C header-like listing of the FFI surface
+    CHeader,
+}
+
+/// A list of directories, files and modules that apicheck should ignore.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct IgnoreList {
-    /// A set of path specified in rustfmt.toml.
-    path_set: HashSet<PathBuf>,
-    /// A path to rustfmt.toml.
+    /// The patterns, in the order they were given: a later `!pattern` re-includes what an
+    /// earlier pattern ignored.
+    path_set: Vec<PathBuf>,
+    /// The directory of the configuration file, to which file patterns are relative.
     rustfmt_toml_path: PathBuf,
 }
 
@@ -28,6 +94,12 @@ impl IgnoreList {
         self.rustfmt_toml_path = dir.to_path_buf();
     }
 
+    /// Add a gitignore-style pattern, relative to the directory set with `add_prefix`, or a
+    /// module path pattern like `crate::tests::**`.
+    pub fn add_pattern(&mut self, pattern: &str) {
+        self.path_set.push(PathBuf::from(pattern));
+    }
+
     pub fn rustfmt_toml_path(&self) -> &Path {
         &self.rustfmt_toml_path
     }
@@ -35,7 +107,7 @@ impl IgnoreList {
 
 impl<'a> IntoIterator for &'a IgnoreList {
     type Item = &'a PathBuf;
-    type IntoIter = hash_set::Iter<'a, PathBuf>;
+    type IntoIter = slice::Iter<'a, PathBuf>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.path_set.iter()
@@ -56,7 +128,22 @@ impl Default for Config {
             debug: 0,
             edition: Edition::Edition2018,
             output: FileName::Stdout,
+            output_format: OutputFormat::Compact,
+            canonical: false,
+            ffi: false,
+            unsafe_surface: false,
+            public_dependencies: false,
+            resolve_paths: false,
+            auto_traits: false,
+            cfg: None,
+            feature_sets: None,
+            feature_matrix: false,
+            expand_macros: false,
+            filter: ItemFilter::default(),
             hide_parse_errors: false,
+            edition_set: false,
+            edition_from_manifest: false,
+            ignore: IgnoreList::default(),
         }
     }
 }
@@ -67,8 +154,182 @@ impl Config {
     }
 
     pub fn ignore(&self) -> IgnoreList {
-        IgnoreList::default()
+        self.ignore.clone()
+    }
+
+    /// Returns `true` if the names in scope in each module are needed to resolve paths.
+    pub(crate) fn needs_scopes(&self) -> bool {
+        self.public_dependencies || self.resolve_paths || self.auto_traits
+    }
+
+    /// Ignore the files matching a gitignore-style pattern.
+    ///
+    /// The pattern is relative to the directory of the configuration file, or to the current
+    /// directory if no configuration file sets an ignore list.
+    pub fn add_ignore_pattern(&mut self, pattern: &str) -> Result<(), ErrorKind> {
+        if self.ignore.rustfmt_toml_path().as_os_str().is_empty() {
+            self.ignore.add_prefix(&env::current_dir()?);
+        }
+        self.ignore.add_pattern(pattern);
+        Ok(())
+    }
+
+    /// Set the edition (`2015`, `2018` or `2021`).
+    pub fn set_edition(&mut self, edition: &str) -> Result<(), ErrorKind> {
+        self.edition = Edition::from_str(edition)
+            .map_err(|_| ErrorKind::InvalidEdition(edition.to_owned()))?;
+        self.edition_set = true;
+        Ok(())
     }
+
+    /// Use the edition declared in a manifest, unless an edition was set explicitly.
+    pub fn set_default_edition(&mut self, edition: Edition) {
+        if !self.edition_set {
+            self.edition = edition;
+        }
+        self.edition_from_manifest = true;
+    }
+
+    /// Resolve the feature sets against the features declared in a manifest: with
+    /// `feature_matrix`, every combination of them, and enabling a feature also enables the
+    /// features it depends on.
+    pub fn resolve_feature_sets(&mut self, manifest: Option<&Manifest>) -> Result<(), ErrorKind> {
+        let sets = match (self.feature_matrix, manifest) {
+            (true, Some(manifest)) => manifest.feature_combinations()?,
+            (true, None) => return Err(ErrorKind::FeatureMatrixWithoutManifest),
+            (false, _) => match self.feature_sets.take() {
+                Some(sets) => sets,
+                None => return Ok(()),
+            },
+        };
+        let mut resolved: Vec<Vec<String>> = Vec::new();
+        for set in sets {
+            let set = match manifest {
+                Some(manifest) => manifest.resolve_features(&set),
+                None => set,
+            };
+            if !resolved.contains(&set) {
+                resolved.push(set);
+            }
+        }
+        self.feature_sets = Some(resolved);
+        Ok(())
+    }
+
+    /// The edition was set explicitly or read from a manifest, rather than being the default.
+    pub fn edition_is_known(&self) -> bool {
+        self.edition_set || self.edition_from_manifest
+    }
+
+    /// Look for a configuration file in `dir` and its parents.
+    pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .flat_map(|dir| CONFIG_FILE_NAMES.iter().map(move |name| dir.join(name)))
+            .find(|path| path.is_file())
+    }
+
+    /// Update the configuration with the settings of the configuration file `path`.
+    ///
+    /// Patterns of the ignore list are relative to the directory of the file.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), ErrorKind> {
+        let invalid = |msg: &str| ErrorKind::InvalidConfig(path.to_path_buf(), msg.to_owned());
+        let content = fs::read_to_string(path)?;
+        let value: toml::Value = content
+            .parse()
+            .map_err(|e: toml::de::Error| invalid(&e.to_string()))?;
+        let table = value.as_table().ok_or_else(|| invalid("not a table"))?;
+        for (key, value) in table {
+            match key.as_str() {
+                "edition" => {
+                    let edition = value
+                        .as_str()
+                        .ok_or_else(|| invalid("edition must be a string"))?;
+                    self.set_edition(edition)?;
+                }
+                "output_format" => {
+                    self.output_format = match value.as_str() {
+                        Some("compact") => OutputFormat::Compact,
+                        Some("pretty") => OutputFormat::Pretty,
+                        Some("c_header") => OutputFormat::CHeader,
+                        _ => {
+                            return Err(invalid(
+                                "output_format must be \"compact\", \"pretty\" or \"c_header\"",
+                            ))
+                        }
+                    };
+                }
+                "canonical" => {
+                    self.canonical = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("canonical must be a boolean"))?;
+                }
+                "ffi" => {
+                    self.ffi = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("ffi must be a boolean"))?;
+                }
+                "unsafe_surface" => {
+                    self.unsafe_surface = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("unsafe_surface must be a boolean"))?;
+                }
+                "public_dependencies" => {
+                    self.public_dependencies = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("public_dependencies must be a boolean"))?;
+                }
+                "resolve_paths" => {
+                    self.resolve_paths = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("resolve_paths must be a boolean"))?;
+                }
+                "auto_traits" => {
+                    self.auto_traits = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("auto_traits must be a boolean"))?;
+                }
+                "ignore" => {
+                    let dir = match path.parent() {
+                        Some(dir) if !dir.as_os_str().is_empty() => dir,
+                        _ => Path::new("."),
+                    };
+                    self.ignore.add_prefix(&fs::canonicalize(dir)?);
+                    for pattern in string_array(value)
+                        .ok_or_else(|| invalid("ignore must be an array of strings"))?
+                    {
+                        self.ignore.add_pattern(pattern);
+                    }
+                }
+                "cfg" => {
+                    let mut cfg = self.cfg.take().unwrap_or_default();
+                    for spec in string_array(value)
+                        .ok_or_else(|| invalid("cfg must be an array of strings"))?
+                    {
+                        cfg.insert_str(spec);
+                    }
+                    self.cfg = Some(cfg);
+                }
+                "include" | "exclude" | "include_kinds" | "exclude_kinds" => {
+                    let values = string_array(value)
+                        .ok_or_else(|| invalid(&format!("{} must be an array of strings", key)))?;
+                    for v in values {
+                        match key.as_str() {
+                            "include" => self.filter.include(v),
+                            "exclude" => self.filter.exclude(v),
+                            "include_kinds" => self.filter.include_kind(v),
+                            _ => self.filter.exclude_kind(v),
+                        }
+                    }
+                }
+                _ => return Err(invalid(&format!("unknown option `{}`", key))),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn string_array(value: &toml::Value) -> Option<Vec<&str>> {
+    value.as_array()?.iter().map(|v| v.as_str()).collect()
 }
 
 impl From<rustc_span::FileName> for FileName {
@@ -1,10 +1,20 @@
 use crate::config::Config;
+use crate::macros::MacroDefs;
+use crate::syntux::session::ParseSess;
 use rustc_ast_pretty::pprust::state::State;
+use std::cell::RefCell;
 
 pub struct Context<'a> {
     pub config: &'a Config,
 
     state: State<'a>,
+
+    parse_sess: Option<&'a ParseSess>,
+
+    macros: Option<&'a MacroDefs>,
+
+    /// Macro invocations which could not be expanded.
+    unexpanded: RefCell<Vec<String>>,
 }
 
 impl<'a> Context<'a> {
@@ -12,10 +22,41 @@ impl<'a> Context<'a> {
         Context {
             config,
             state: State::new(),
+            parse_sess: None,
+            macros: None,
+            unexpanded: RefCell::new(Vec::new()),
         }
     }
 
+    /// Set the parsing session, used to parse macro invocations like `cfg_if!`.
+    pub(crate) fn with_parse_sess(mut self, parse_sess: &'a ParseSess) -> Self {
+        self.parse_sess = Some(parse_sess);
+        self
+    }
+
+    /// Set the macros to expand.
+    pub(crate) fn with_macros(mut self, macros: &'a MacroDefs) -> Self {
+        self.macros = Some(macros);
+        self
+    }
+
     pub fn print_state(&self) -> &'a State {
         &self.state
     }
+
+    pub(crate) fn parse_sess(&self) -> Option<&'a ParseSess> {
+        self.parse_sess
+    }
+
+    pub(crate) fn macros(&self) -> Option<&'a MacroDefs> {
+        self.macros
+    }
+
+    pub(crate) fn add_unexpanded(&self, description: String) {
+        self.unexpanded.borrow_mut().push(description);
+    }
+
+    pub(crate) fn unexpanded(&self) -> Vec<String> {
+        self.unexpanded.borrow().clone()
+    }
 }
@@ -0,0 +1,172 @@
+//! Extraction of the API for several feature sets (feature matrix).
+//!
+//! The API is extracted once per feature set, and the results are merged into
+//! a single description where each item lists the feature sets enabling it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use json::JsonValue;
+
+use crate::cfg::CfgSet;
+use crate::config::Config;
+use crate::{process_project, ErrorKind, Input};
+
+struct MergedItem {
+    item: JsonValue,
+    runs: Vec<usize>,
+    children: Vec<MergedItem>,
+}
+
+pub(crate) fn extract_feature_matrix(
+    input: &Input,
+    config: &Config,
+    feature_sets: &[Vec<String>],
+) -> Result<JsonValue, ErrorKind> {
+    let mut runs = Vec::new();
+    for features in feature_sets {
+        let mut run_config = config.clone();
+        // without explicit cfg options, items are evaluated for the host
+        let mut cfg = config.cfg.clone().unwrap_or_else(CfgSet::host);
+        for feature in features {
+            cfg.insert_feature(feature);
+        }
+        run_config.cfg = Some(cfg);
+        run_config.feature_sets = None;
+        if config.debug > 0 {
+            println!("Extracting API with features {:?}", features);
+        }
+        runs.push(process_project(input.clone(), &run_config)?);
+    }
+    Ok(merge_runs(feature_sets, &runs))
+}
+
+/// Merge the descriptions extracted for each feature set, starting from the description of the
+/// first feature set, so that the keys which do not depend on the features (like `crate` or
+/// `resolve_paths`) are kept.
+fn merge_runs(feature_sets: &[Vec<String>], runs: &[JsonValue]) -> JsonValue {
+    let mut modules: Vec<(JsonValue, Vec<MergedItem>)> = Vec::new();
+    for (run, js) in runs.iter().enumerate() {
+        for module in js["modules"].members() {
+            let pos = modules
+                .iter()
+                .position(|(m, _)| m["path"] == module["path"]);
+            let idx = match pos {
+                Some(idx) => idx,
+                None => {
+                    // the items are replaced by the merged items
+                    modules.push((module.clone(), Vec::new()));
+                    modules.len() - 1
+                }
+            };
+            let (ref mut mod_json, ref mut items) = modules[idx];
+            merge_items(items, &module["items"], run);
+            // the imports of all the feature sets
+            for (name, import) in module["imports"].entries() {
+                mod_json["imports"][name] = import.clone();
+            }
+        }
+    }
+
+    let mod_v: Vec<JsonValue> = modules
+        .into_iter()
+        .map(|(mut mod_json, items)| {
+            mod_json["items"] = merged_to_json(items, feature_sets);
+            mod_json
+        })
+        .collect();
+
+    let mut json = runs.first().cloned().unwrap_or_else(JsonValue::new_object);
+    json["modules"] = JsonValue::Array(mod_v);
+    if runs.iter().any(|js| js.has_key("public_dependencies")) {
+        json["public_dependencies"] = merge_dependencies(runs);
+    }
+    json["feature_sets"] = JsonValue::Array(
+        feature_sets
+            .iter()
+            .map(|features| feature_set_to_json(features))
+            .collect(),
+    );
+    json
+}
+
+/// Merge the public dependencies of all the runs.
+fn merge_dependencies(runs: &[JsonValue]) -> JsonValue {
+    let mut dependencies: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for js in runs {
+        for (krate, items) in js["public_dependencies"].entries() {
+            dependencies
+                .entry(krate)
+                .or_insert_with(BTreeSet::new)
+                .extend(items.members().filter_map(|item| item.as_str()));
+        }
+    }
+    let mut json = JsonValue::new_object();
+    for (krate, items) in dependencies {
+        json[krate] = JsonValue::Array(items.into_iter().map(JsonValue::from).collect());
+    }
+    json
+}
+
+fn merge_items(merged: &mut Vec<MergedItem>, items: &JsonValue, run: usize) {
+    for item in items.members() {
+        if item["type"] == "mod" {
+            // modules are merged by name, their items are merged recursively
+            let mut mod_item = item.clone();
+            mod_item.remove("items");
+            let pos = merged
+                .iter()
+                .position(|m| m.item["type"] == "mod" && m.item["name"] == item["name"]);
+            let idx = match pos {
+                Some(idx) => idx,
+                None => {
+                    merged.push(MergedItem {
+                        item: mod_item,
+                        runs: Vec::new(),
+                        children: Vec::new(),
+                    });
+                    merged.len() - 1
+                }
+            };
+            merged[idx].runs.push(run);
+            merge_items(&mut merged[idx].children, &item["items"], run);
+            continue;
+        }
+        match merged.iter_mut().find(|m| m.item == *item) {
+            Some(m) => m.runs.push(run),
+            None => merged.push(MergedItem {
+                item: item.clone(),
+                runs: vec![run],
+                children: Vec::new(),
+            }),
+        }
+    }
+}
+
+fn merged_to_json(merged: Vec<MergedItem>, feature_sets: &[Vec<String>]) -> JsonValue {
+    let v: Vec<JsonValue> = merged
+        .into_iter()
+        .map(|m| {
+            let mut js = m.item;
+            if js["type"] == "mod" {
+                js["items"] = merged_to_json(m.children, feature_sets);
+            }
+            js["features"] = JsonValue::Array(
+                m.runs
+                    .iter()
+                    .map(|&run| feature_set_to_json(&feature_sets[run]))
+                    .collect(),
+            );
+            js
+        })
+        .collect();
+    JsonValue::Array(v)
+}
+
+fn feature_set_to_json(features: &[String]) -> JsonValue {
+    JsonValue::Array(
+        features
+            .iter()
+            .map(|f| JsonValue::String(f.clone()))
+            .collect(),
+    )
+}
@@ -0,0 +1,450 @@
+//! FFI surface: the part of the API visible from C.
+//!
+//! The FFI surface contains the functions and statics exported with `#[no_mangle]` or
+//! `#[export_name]`, the functions with an explicit ABI (`extern "C"`), the types with a C
+//! compatible representation (`#[repr(C)]`, `#[repr(transparent)]` or an integer
+//! representation), and the function pointer types used by them. It is a regular API
+//! description, which can be compared with `apidiff`, or printed as a C header-like listing.
+
+use std::collections::HashSet;
+
+use json::JsonValue;
+
+/// Keep only the FFI surface of an API description.
+pub fn ffi_surface(json: &mut JsonValue) {
+    if json.has_key("crates") {
+        for (_, krate) in json["crates"].entries_mut() {
+            ffi_surface(krate);
+        }
+        return;
+    }
+    // function pointer types are kept if the other items use them
+    let mut used = HashSet::new();
+    for module in json["modules"].members() {
+        collect_used_types(&module["items"], &mut used);
+    }
+    let modules: Vec<JsonValue> = json["modules"]
+        .members()
+        .filter_map(|module| filter_module(module.clone(), &used))
+        .collect();
+    json["modules"] = JsonValue::Array(modules);
+}
+
+fn filter_module(mut module: JsonValue, used: &HashSet<String>) -> Option<JsonValue> {
+    let items: Vec<JsonValue> = module["items"]
+        .members()
+        .filter_map(|item| {
+            if item["type"] == "mod" {
+                filter_module(item.clone(), used)
+            } else if is_ffi_item(item) || is_used_fn_pointer(item, used) {
+                Some(item.clone())
+            } else {
+                None
+            }
+        })
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+    module["items"] = JsonValue::Array(items);
+    Some(module)
+}
+
+fn is_ffi_item(item: &JsonValue) -> bool {
+    match item["type"].as_str() {
+        Some("function") => has_c_abi(item) || is_exported(item),
+        Some("static") => is_exported(item),
+        Some("struct") | Some("union") | Some("enum") => has_c_repr(item),
+        _ => false,
+    }
+}
+
+fn is_used_fn_pointer(item: &JsonValue, used: &HashSet<String>) -> bool {
+    item["type"] == "type"
+        && item["subtype"].as_str().is_some_and(is_fn_pointer)
+        && item["name"]
+            .as_str()
+            .is_some_and(|name| used.contains(name))
+}
+
+fn has_c_abi(item: &JsonValue) -> bool {
+    match item["extern"].as_str() {
+        Some("") | Some("Rust") | None => false,
+        Some(_) => true,
+    }
+}
+
+/// Returns `true` for items with a symbol name set by `#[no_mangle]` or `#[export_name]`.
+fn is_exported(item: &JsonValue) -> bool {
+    item["attrs"]
+        .members()
+        .filter_map(|a| a.as_str())
+        .any(|a| a.starts_with("#[no_mangle") || a.starts_with("#[export_name"))
+}
+
+/// Returns the name of the symbol of an exported item.
+fn symbol_name(item: &JsonValue) -> Option<&str> {
+    for attr in item["attrs"].members().filter_map(|a| a.as_str()) {
+        if attr.starts_with("#[no_mangle") {
+            return item["name"].as_str();
+        }
+        if attr.starts_with("#[export_name") {
+            // `#[export_name = "name"]`
+            return attr.split('"').nth(1);
+        }
+    }
+    None
+}
+
+fn has_c_repr(item: &JsonValue) -> bool {
+    item["repr"]
+        .members()
+        .filter_map(|r| r.as_str())
+        .any(|r| r == "C" || r == "transparent" || integer_type(r).is_some())
+}
+
+fn is_fn_pointer(ty: &str) -> bool {
+    let ty = strip_option(ty);
+    ty.starts_with("extern ") || ty.starts_with("unsafe extern ") || ty.starts_with("fn(")
+}
+
+/// Collect the names used in the types of FFI items.
+fn collect_used_types(items: &JsonValue, used: &mut HashSet<String>) {
+    for item in items.members() {
+        if item["type"] == "mod" {
+            collect_used_types(&item["items"], used);
+            continue;
+        }
+        if !is_ffi_item(item) {
+            continue;
+        }
+        let mut types = vec![item["output"].as_str(), item["subtype"].as_str()];
+        types.extend(item["inputs"].members().map(|i| i["type"].as_str()));
+        types.extend(item["fields"].members().map(|f| f["type"].as_str()));
+        for variant in item["fields"].members() {
+            types.extend(variant["fields"].members().map(|f| f["type"].as_str()));
+        }
+        for ty in types.into_iter().flatten() {
+            used.extend(
+                ty.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_owned()),
+            );
+        }
+    }
+}
+
+/// Print the FFI surface as a C header-like listing.
+///
+/// Types which cannot be translated are printed with their Rust names.
+pub fn c_header(json: &JsonValue) -> String {
+    let mut json = json.clone();
+    ffi_surface(&mut json);
+    let mut types = String::new();
+    let mut statics = String::new();
+    let mut functions = String::new();
+    let mut modules: Vec<&JsonValue> = json["modules"].members().collect();
+    for (_, krate) in json["crates"].entries() {
+        modules.extend(krate["modules"].members());
+    }
+    for module in modules {
+        write_items(&module["items"], &mut types, &mut statics, &mut functions);
+    }
+    let mut header = String::from(
+        "/* FFI surface, generated by apicheck */\n\n\
+         #include <stdbool.h>\n\
+         #include <stddef.h>\n\
+         #include <stdint.h>\n",
+    );
+    for section in &[types, statics, functions] {
+        if !section.is_empty() {
+            header.push('\n');
+            header.push_str(section.trim_end());
+            header.push('\n');
+        }
+    }
+    header
+}
+
+fn write_items(
+    items: &JsonValue,
+    types: &mut String,
+    statics: &mut String,
+    functions: &mut String,
+) {
+    for item in items.members() {
+        let name = item["name"].as_str().unwrap_or("");
+        match item["type"].as_str() {
+            Some("mod") => write_items(&item["items"], types, statics, functions),
+            Some("type") => {
+                let ty = item["subtype"].as_str().unwrap_or("");
+                types.push_str(&format!("typedef {};\n\n", c_decl(ty, name)));
+            }
+            Some(kind @ "struct") | Some(kind @ "union") => {
+                types.push_str(&format!("typedef {} {} {{\n", kind, name));
+                for (idx, field) in item["fields"].members().enumerate() {
+                    let field_name = match field["name"].as_str() {
+                        Some(n) if n != "<anon>" => n.to_owned(),
+                        _ => format!("_{}", idx),
+                    };
+                    let ty = field["type"].as_str().unwrap_or("");
+                    types.push_str(&format!("    {};\n", c_decl(ty, &field_name)));
+                }
+                types.push_str(&format!("}} {};\n\n", name));
+            }
+            Some("enum") => write_enum(item, name, types),
+            Some("static") => {
+                let ty = item["subtype"].as_str().unwrap_or("");
+                let symbol = symbol_name(item).unwrap_or(name);
+                let decl = c_qualified_decl(ty, symbol, item["mutability"] != "mut");
+                statics.push_str(&format!("extern {};\n", decl));
+            }
+            Some("function") => {
+                let symbol = match symbol_name(item) {
+                    Some(symbol) => symbol,
+                    None => {
+                        functions.push_str("/* mangled symbol, no #[no_mangle] */\n");
+                        name
+                    }
+                };
+                // the `...` argument of C-variadic functions is printed by `params`
+                let inputs: Vec<String> = item["inputs"]
+                    .members()
+                    .filter(|i| i["type"] != "...")
+                    .map(|i| {
+                        let ty = i["type"].as_str().unwrap_or("");
+                        c_decl(ty, i["name"].as_str().unwrap_or(""))
+                    })
+                    .collect();
+                let output = item["output"].as_str().unwrap_or("");
+                let decl = format!("{}({})", symbol, params(inputs, item["variadic"] == true));
+                functions.push_str(&format!("{};\n", c_decl(output, &decl)));
+            }
+            _ => (),
+        }
+    }
+}
+
+fn write_enum(item: &JsonValue, name: &str, types: &mut String) {
+    if item["fields"].members().any(|v| !v["fields"].is_empty()) {
+        types.push_str(&format!(
+            "/* enum {} has variants with fields, not translated */\n\n",
+            name
+        ));
+        return;
+    }
+    let repr = item["repr"]
+        .members()
+        .filter_map(|r| r.as_str())
+        .filter_map(integer_type)
+        .next();
+    types.push_str(&format!("enum {} {{\n", name));
+    for variant in item["fields"].members() {
+        let variant_name = variant["name"].as_str().unwrap_or("");
+        match variant["discriminant"].as_str() {
+            Some(d) => types.push_str(&format!("    {}_{} = {},\n", name, variant_name, d)),
+            None => types.push_str(&format!("    {}_{},\n", name, variant_name)),
+        }
+    }
+    types.push_str("};\n");
+    match repr {
+        Some(ty) => types.push_str(&format!("typedef {} {};\n\n", ty, name)),
+        None => types.push_str(&format!("typedef enum {0} {0};\n\n", name)),
+    }
+}
+
+fn params(mut inputs: Vec<String>, variadic: bool) -> String {
+    if variadic {
+        inputs.push("...".to_owned());
+    }
+    if inputs.is_empty() {
+        "void".to_owned()
+    } else {
+        inputs.join(", ")
+    }
+}
+
+/// Returns the C declaration of `decl` (a name, possibly with declarators) of Rust type `ty`.
+fn c_decl(ty: &str, decl: &str) -> String {
+    c_qualified_decl(ty, decl, false)
+}
+
+/// Returns the C declaration of `decl` of Rust type `ty`, `is_const` if the declared object
+/// is `const`.
+///
+/// The declarator is built from the inside out, so that the qualifiers of pointers apply to
+/// the right level: `*const *mut T` is `T *const *`.
+fn c_qualified_decl(ty: &str, decl: &str, is_const: bool) -> String {
+    let ty = ty.trim();
+    // `&mut` before `&`
+    let pointer = [
+        ("*const ", true),
+        ("*mut ", false),
+        ("&mut ", false),
+        ("&", true),
+    ]
+    .iter()
+    .find_map(|&(prefix, is_const)| ty.strip_prefix(prefix).map(|pointee| (is_const, pointee)));
+    if let Some((pointee_const, pointee)) = pointer {
+        return c_qualified_decl(pointee, &pointer_decl(decl, is_const), pointee_const);
+    }
+    if ty.starts_with('[') && ty.ends_with(']') {
+        if let Some(idx) = ty.rfind(';') {
+            let decl = if decl.starts_with('*') {
+                format!("({})", decl)
+            } else {
+                decl.to_owned()
+            };
+            let len = ty[idx + 1..ty.len() - 1].trim();
+            // the elements of a const array are const
+            return c_qualified_decl(&ty[1..idx], &format!("{}[{}]", decl, len), is_const);
+        }
+    }
+    if is_fn_pointer(ty) {
+        let ty = strip_option(ty);
+        if let Some((inputs, output)) = split_fn_pointer(ty) {
+            let inputs: Vec<String> = inputs.iter().map(|&(name, ty)| c_decl(ty, name)).collect();
+            let variadic = inputs.last().map(String::as_str) == Some("...");
+            let mut inputs = inputs;
+            if variadic {
+                inputs.pop();
+            }
+            let pointer = pointer_decl(decl, is_const);
+            let decl = format!("({})({})", pointer, params(inputs, variadic));
+            return c_decl(output, &decl);
+        }
+    }
+    let base = c_base_type(ty);
+    let base = if is_const {
+        format!("const {}", base)
+    } else {
+        base
+    };
+    if decl.is_empty() {
+        base
+    } else {
+        format!("{} {}", base, decl)
+    }
+}
+
+/// Returns the declarator of a pointer to `decl`, like `*const name` for a const pointer.
+fn pointer_decl(decl: &str, is_const: bool) -> String {
+    if is_const {
+        format!("*const {}", decl).trim_end().to_owned()
+    } else {
+        format!("*{}", decl)
+    }
+}
+
+fn c_base_type(ty: &str) -> String {
+    if let Some(int) = integer_type(ty) {
+        return int.to_owned();
+    }
+    let name = ty.rsplit("::").next().unwrap_or(ty);
+    let c = match name {
+        "" | "()" | "c_void" => "void",
+        "bool" => "bool",
+        "f32" | "c_float" => "float",
+        "f64" | "c_double" => "double",
+        "c_char" => "char",
+        "c_schar" => "signed char",
+        "c_uchar" => "unsigned char",
+        "c_short" => "short",
+        "c_ushort" => "unsigned short",
+        "c_int" => "int",
+        "c_uint" => "unsigned int",
+        "c_long" => "long",
+        "c_ulong" => "unsigned long",
+        "c_longlong" => "long long",
+        "c_ulonglong" => "unsigned long long",
+        "..." => "...",
+        _ => return name.to_owned(),
+    };
+    c.to_owned()
+}
+
+fn integer_type(ty: &str) -> Option<&'static str> {
+    let c = match ty {
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "i8" => "int8_t",
+        "i16" => "int16_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "usize" => "size_t",
+        "isize" => "ptrdiff_t",
+        _ => return None,
+    };
+    Some(c)
+}
+
+/// `Option<fn(..)>` is a nullable function pointer.
+fn strip_option(ty: &str) -> &str {
+    let ty = ty.trim();
+    if ty.starts_with("Option<") && ty.ends_with('>') {
+        ty[7..ty.len() - 1].trim()
+    } else {
+        ty
+    }
+}
+
+/// Split a function pointer type into its arguments (name and type) and its return type.
+fn split_fn_pointer(ty: &str) -> Option<(Vec<(&str, &str)>, &str)> {
+    let start = ty.find("fn(")? + 3;
+    let mut depth = 0;
+    let mut end = None;
+    let mut prev = ' ';
+    for (idx, c) in ty[start..].char_indices() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            // `->` in a nested function pointer
+            '>' if prev == '-' => (),
+            ')' | '>' | ']' if depth > 0 => depth -= 1,
+            ')' => {
+                end = Some(start + idx);
+                break;
+            }
+            _ => (),
+        }
+        prev = c;
+    }
+    let end = end?;
+    let inputs = split_top_level(&ty[start..end])
+        .into_iter()
+        .map(|arg| match arg.find(':') {
+            // named arguments, like `fn(len: usize)`
+            Some(idx) if !arg[idx..].starts_with("::") => {
+                (arg[..idx].trim(), arg[idx + 1..].trim())
+            }
+            _ => ("", arg),
+        })
+        .collect();
+    let output = ty[end + 1..].trim();
+    let output = output.strip_prefix("->").map_or("", str::trim);
+    Some((inputs, output))
+}
+
+/// Split a list of types at the commas which are not nested in brackets.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut prev = ' ';
+    for (idx, c) in s.char_indices() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            '>' if prev == '-' => (),
+            ')' | '>' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => (),
+        }
+        prev = c;
+    }
+    parts.push(s[start..].trim());
+    parts.into_iter().filter(|p| !p.is_empty()).collect()
+}
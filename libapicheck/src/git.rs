@@ -0,0 +1,48 @@
+//! Access to the files of a git repository at a given revision.
+//!
+//! Files are read from the object database with `git archive`, so the working tree and
+//! the index of the repository are left untouched.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tempfile::TempDir;
+
+use crate::ErrorKind;
+
+/// Returns the root of the working tree of the repository containing `path`.
+pub fn toplevel(path: &Path) -> Result<PathBuf, ErrorKind> {
+    let output = run_git(path, &["rev-parse", "--show-toplevel"])?;
+    let s = String::from_utf8_lossy(&output);
+    Ok(PathBuf::from(s.trim_end()))
+}
+
+/// Export the files of `rev` (a tag, branch or commit) into a temporary directory.
+///
+/// The whole repository is exported, even if `repo` is a subdirectory. Submodules are
+/// not exported.
+pub fn export_revision(repo: &Path, rev: &str) -> Result<TempDir, ErrorKind> {
+    let top = toplevel(repo)?;
+    let commit = format!("{}^{{commit}}", rev);
+    run_git(&top, &["rev-parse", "--verify", "--quiet", &commit])
+        .map_err(|_| ErrorKind::GitError(format!("unknown revision {:?}", rev)))?;
+    let archive = run_git(&top, &["archive", "--format=tar", &commit])?;
+    let dir = tempfile::Builder::new().prefix("apicheck-").tempdir()?;
+    tar::Archive::new(&archive[..]).unpack(dir.path())?;
+    Ok(dir)
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, ErrorKind> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| ErrorKind::GitError(format!("could not run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(ErrorKind::GitError(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+    Ok(output.stdout)
+}
@@ -1,33 +1,76 @@
+use std::env;
+use std::path::PathBuf;
+
 use ignore::{self, gitignore};
 
 use crate::config::{FileName, IgnoreList};
+use crate::filter::path_matches;
 
 pub(crate) struct IgnorePathSet {
     ignore_set: gitignore::Gitignore,
+    /// Module path patterns, like `crate::tests::**`, possibly negated with `!`
+    module_patterns: Vec<String>,
 }
 
 impl IgnorePathSet {
     pub(crate) fn from_ignore_list(ignore_list: &IgnoreList) -> Result<Self, ignore::Error> {
         let mut ignore_builder = gitignore::GitignoreBuilder::new(ignore_list.rustfmt_toml_path());
+        let mut module_patterns = Vec::new();
 
         for ignore_path in ignore_list {
-            ignore_builder.add_line(None, ignore_path.to_str().unwrap())?;
+            let pattern = ignore_path.to_str().unwrap();
+            if is_module_pattern(pattern) {
+                module_patterns.push(pattern.to_owned());
+            } else {
+                ignore_builder.add_line(None, pattern)?;
+            }
         }
 
         Ok(IgnorePathSet {
             ignore_set: ignore_builder.build()?,
+            module_patterns,
         })
     }
 
     pub(crate) fn is_match(&self, file_name: &FileName) -> bool {
         match file_name {
             FileName::Stdin => false,
-            FileName::Real(p) => self
-                .ignore_set
-                .matched_path_or_any_parents(p, false)
-                .is_ignore(),
+            FileName::Real(_) if self.ignore_set.is_empty() => false,
+            FileName::Real(p) => {
+                // patterns are relative to an absolute directory
+                let path: PathBuf = match env::current_dir() {
+                    Ok(dir) if p.is_relative() => dir.join(p).components().collect(),
+                    _ => p.clone(),
+                };
+                // files outside of this directory cannot match
+                path.starts_with(self.ignore_set.path())
+                    && self
+                        .ignore_set
+                        .matched_path_or_any_parents(&path, false)
+                        .is_ignore()
+            }
             FileName::Stdout => false,
         }
     }
+
+    /// Returns `true` if the module with the logical path `path` (like `crate::a::b`) is
+    /// ignored. As in gitignore files, the last matching pattern wins.
+    pub(crate) fn is_module_match(&self, path: &str) -> bool {
+        let mut ignored = false;
+        for pattern in &self.module_patterns {
+            let (negated, pattern) = match pattern.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern.as_str()),
+            };
+            if path_matches(pattern, path) {
+                ignored = !negated;
+            }
+        }
+        ignored
+    }
 }
 
+/// Returns `true` for module path patterns, which start with `crate::`.
+fn is_module_pattern(pattern: &str) -> bool {
+    pattern.trim_start_matches('!').starts_with("crate::")
+}
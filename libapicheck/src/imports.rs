@@ -0,0 +1,570 @@
+//! Resolution of the paths used in types, with the `use` declarations, `extern crate` items
+//! and item names of each module (its scope), and the prelude.
+//!
+//! Scopes are collected during the extraction in the `scope` key of modules, and removed
+//! from the output with `remove_scopes`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use json::JsonValue;
+use rustc_span::source_map::edition::Edition;
+
+/// Crates of the standard library, which are not dependencies.
+const STD_CRATES: &[&str] = &["std", "core", "alloc", "proc_macro", "test"];
+
+/// Names of the prelude, with their canonical paths.
+const PRELUDE: &[(&str, &str)] = &[
+    ("AsMut", "std::convert::AsMut"),
+    ("AsRef", "std::convert::AsRef"),
+    ("Box", "std::boxed::Box"),
+    ("Clone", "std::clone::Clone"),
+    ("Copy", "std::marker::Copy"),
+    ("Default", "std::default::Default"),
+    ("DoubleEndedIterator", "std::iter::DoubleEndedIterator"),
+    ("Drop", "std::ops::Drop"),
+    ("Eq", "std::cmp::Eq"),
+    ("ExactSizeIterator", "std::iter::ExactSizeIterator"),
+    ("Extend", "std::iter::Extend"),
+    ("Fn", "std::ops::Fn"),
+    ("FnMut", "std::ops::FnMut"),
+    ("FnOnce", "std::ops::FnOnce"),
+    ("From", "std::convert::From"),
+    ("Into", "std::convert::Into"),
+    ("IntoIterator", "std::iter::IntoIterator"),
+    ("Iterator", "std::iter::Iterator"),
+    ("Option", "std::option::Option"),
+    ("Ord", "std::cmp::Ord"),
+    ("PartialEq", "std::cmp::PartialEq"),
+    ("PartialOrd", "std::cmp::PartialOrd"),
+    ("Result", "std::result::Result"),
+    ("Send", "std::marker::Send"),
+    ("Sized", "std::marker::Sized"),
+    ("String", "std::string::String"),
+    ("Sync", "std::marker::Sync"),
+    ("ToOwned", "std::borrow::ToOwned"),
+    ("ToString", "std::string::ToString"),
+    ("Unpin", "std::marker::Unpin"),
+    ("Vec", "std::vec::Vec"),
+];
+
+/// Names in scope in a module.
+struct Scope<'a> {
+    json: &'a JsonValue,
+    /// Logical path of the module, like `crate::a::b`
+    module: &'a str,
+}
+
+impl<'a> Scope<'a> {
+    fn import(&self, name: &str) -> Option<&'a str> {
+        self.json["imports"][name].as_str()
+    }
+
+    fn defines(&self, name: &str) -> bool {
+        defines(self.json, name)
+    }
+
+    /// Paths of the modules imported with `use path::*`.
+    fn globs(&self) -> impl Iterator<Item = &'a str> {
+        self.json["globs"].members().filter_map(|g| g.as_str())
+    }
+}
+
+/// Returns `true` if the scope `json` of a module defines `name`.
+fn defines(json: &JsonValue, name: &str) -> bool {
+    json["names"].members().any(|n| n == name)
+}
+
+/// Resolution of paths to canonical paths: paths starting with `crate::` for the items of the
+/// crate, with the name of a crate for external items, or single names for generic
+/// parameters and primitive types.
+struct Resolver {
+    edition: Edition,
+    /// Crates declared with `extern crate`
+    extern_crates: HashSet<String>,
+    /// Scopes of the modules of the crate, by logical path, for glob imports
+    scopes: HashMap<String, JsonValue>,
+}
+
+impl Resolver {
+    fn new(json: &JsonValue, edition: Edition) -> Resolver {
+        let mut extern_crates = HashSet::new();
+        let mut scopes = HashMap::new();
+        for module in json["modules"].members() {
+            collect_extern_crates(module, &mut extern_crates);
+            let path = module["module"].as_str().unwrap_or("crate");
+            collect_scopes(module, path, &mut scopes);
+        }
+        Resolver {
+            edition,
+            extern_crates,
+            scopes,
+        }
+    }
+
+    /// Returns a type with the canonical paths.
+    fn canonical_type(&self, ty: &str, scope: &Scope) -> String {
+        let mut canonical = String::new();
+        let mut last = 0;
+        for (start, end) in type_paths(ty) {
+            canonical.push_str(&ty[last..start]);
+            canonical.push_str(&self.canonical_type_path(&ty[start..end], scope));
+            last = end;
+        }
+        canonical.push_str(&ty[last..]);
+        canonical
+    }
+
+    /// Returns the canonical form of a path used in a type.
+    fn canonical_type_path(&self, path: &str, scope: &Scope) -> String {
+        if path.starts_with("::") {
+            return self.canonical_absolute_path(&path[2..]);
+        }
+        let (first, rest) = match path.find("::") {
+            Some(idx) => (&path[..idx], Some(&path[idx..])),
+            None => (path, None),
+        };
+        if first == "Self" {
+            return path.to_owned();
+        }
+        if is_local_keyword(first) {
+            return relative_path(path, scope);
+        }
+        if let Some(import) = scope.import(first) {
+            let import = self.canonical_use_path(import, scope);
+            return format!("{}{}", import, rest.unwrap_or(""));
+        }
+        if scope.defines(first) {
+            return format!("{}::{}", scope.module, path);
+        }
+        if let Some(module) = self.glob_module_defining(first, scope) {
+            return format!("{}::{}", module, path);
+        }
+        match rest {
+            // generic parameters, primitive types and names of the prelude
+            None => PRELUDE
+                .iter()
+                .find(|&&(name, _)| name == path)
+                .map_or_else(|| path.to_owned(), |&(_, canonical)| canonical.to_owned()),
+            Some(_) => normalize_std(path),
+        }
+    }
+
+    /// Returns the module of the crate imported with a glob (`use super::*`) which defines
+    /// `name`, if any.
+    fn glob_module_defining(&self, name: &str, scope: &Scope) -> Option<String> {
+        scope
+            .globs()
+            .map(|glob| self.canonical_use_path(glob, scope))
+            .find(|module| {
+                self.scopes
+                    .get(module)
+                    .is_some_and(|json| defines(json, name))
+            })
+    }
+
+    /// Returns the canonical form of the path of a `use` declaration.
+    fn canonical_use_path(&self, path: &str, scope: &Scope) -> String {
+        if path.starts_with("::") {
+            return self.canonical_absolute_path(&path[2..]);
+        }
+        let first = path.split("::").next().unwrap_or("");
+        if is_local_keyword(first) {
+            return relative_path(path, scope);
+        }
+        if self.edition == Edition::Edition2015 {
+            // paths are relative to the crate root
+            if self.extern_crates.contains(first) {
+                normalize_std(path)
+            } else {
+                format!("crate::{}", path)
+            }
+        } else if scope.defines(first) {
+            format!("{}::{}", scope.module, path)
+        } else {
+            normalize_std(path)
+        }
+    }
+
+    /// Returns the canonical form of a path starting with `::`.
+    fn canonical_absolute_path(&self, path: &str) -> String {
+        let first = path.split("::").next().unwrap_or("");
+        if self.edition == Edition::Edition2015 && !self.extern_crates.contains(first) {
+            // `::a::b` is a path in the crate
+            return format!("crate::{}", path);
+        }
+        normalize_std(path)
+    }
+
+    /// Returns the crate of a path used in a type, if it is an external crate.
+    fn crate_of_type_path(&self, path: &str, scope: &Scope) -> Option<String> {
+        crate_of_canonical_path(&self.canonical_type_path(path, scope))
+    }
+
+    /// Returns the crate of the path of a `use` declaration, if it is an external crate.
+    fn crate_of_use_path(&self, path: &str, scope: &Scope) -> Option<String> {
+        crate_of_canonical_path(&self.canonical_use_path(path, scope))
+    }
+}
+
+/// Resolve a path starting with `crate`, `self` or `super`.
+fn relative_path(path: &str, scope: &Scope) -> String {
+    let mut module: Vec<&str> = scope.module.split("::").collect();
+    let mut segments = path.split("::").peekable();
+    loop {
+        match segments.peek() {
+            Some(&"crate") => module.truncate(1),
+            Some(&"self") => (),
+            Some(&"super") => {
+                if module.len() > 1 {
+                    module.pop();
+                }
+            }
+            _ => break,
+        }
+        segments.next();
+    }
+    module.extend(segments);
+    module.join("::")
+}
+
+/// `core` and `alloc` items are re-exported by `std` with the same paths.
+fn normalize_std(path: &str) -> String {
+    for prefix in &["core::", "alloc::"] {
+        if path.starts_with(prefix) {
+            return format!("std::{}", &path[prefix.len()..]);
+        }
+    }
+    path.to_owned()
+}
+
+fn crate_of_canonical_path(path: &str) -> Option<String> {
+    let mut segments = path.split("::");
+    let first = segments.next()?;
+    // single names are not in a crate
+    segments.next()?;
+    // `T::Item`
+    if first == "crate" || first == "Self" || first.starts_with(char::is_uppercase) {
+        return None;
+    }
+    Some(first.to_owned())
+}
+
+fn collect_extern_crates(json: &JsonValue, extern_crates: &mut HashSet<String>) {
+    for (_, path) in json["scope"]["imports"].entries() {
+        if let Some(path) = path.as_str() {
+            if path.starts_with("::") && !path[2..].contains("::") {
+                extern_crates.insert(path[2..].to_owned());
+            }
+        }
+    }
+    for item in json["items"].members() {
+        if item["type"] == "mod" {
+            collect_extern_crates(item, extern_crates);
+        }
+    }
+}
+
+/// Collect the scopes of a module and of its inline modules.
+fn collect_scopes(json: &JsonValue, path: &str, scopes: &mut HashMap<String, JsonValue>) {
+    scopes.insert(path.to_owned(), json["scope"].clone());
+    for item in json["items"].members() {
+        if item["type"] == "mod" {
+            collect_scopes(item, &format!("{}::{}", path, item["name"]), scopes);
+        }
+    }
+}
+
+fn is_local_keyword(segment: &str) -> bool {
+    segment == "crate" || segment == "self" || segment == "super"
+}
+
+/// Add the canonical form of the types of the items, when it differs from the written form,
+/// in `resolved_` keys (`resolved_output` for `output`, ...).
+///
+/// Paths are resolved with the imports and the items of the module, and the prelude.
+pub fn resolve_paths(json: &mut JsonValue, edition: Edition) {
+    let resolver = Resolver::new(json, edition);
+    for module in json["modules"].members_mut() {
+        let path = module["module"].as_str().unwrap_or("crate").to_owned();
+        resolve_module(module, &path, &resolver);
+    }
+}
+
+fn resolve_module(module: &mut JsonValue, path: &str, resolver: &Resolver) {
+    let scope_json = module["scope"].clone();
+    let scope = Scope {
+        json: &scope_json,
+        module: path,
+    };
+    for item in module["items"].members_mut() {
+        if item["type"] == "mod" {
+            let path = format!("{}::{}", path, item["name"]);
+            resolve_module(item, &path, resolver);
+        } else {
+            resolve_item(item, &scope, resolver);
+        }
+    }
+}
+
+fn resolve_item(item: &mut JsonValue, scope: &Scope, resolver: &Resolver) {
+    for key in &[
+        "output",
+        "subtype",
+        "generics",
+        "where",
+        "impl_type",
+        "trait",
+    ] {
+        resolve_key(item, key, scope, resolver);
+    }
+    // indexing a missing key would insert it
+    if item.has_key("inputs") {
+        for input in item["inputs"].members_mut() {
+            resolve_key(input, "type", scope, resolver);
+        }
+    }
+    if item.has_key("typarambounds") {
+        for bound in item["typarambounds"].members_mut() {
+            resolve_key(bound, "trait_ref", scope, resolver);
+        }
+    }
+    if item.has_key("fields") {
+        for field in item["fields"].members_mut() {
+            if field.has_key("fields") {
+                // enum variant
+                for variant_field in field["fields"].members_mut() {
+                    resolve_key(variant_field, "type", scope, resolver);
+                }
+            } else {
+                resolve_key(field, "type", scope, resolver);
+            }
+        }
+    }
+    if item.has_key("items") {
+        for child in item["items"].members_mut() {
+            resolve_item(child, scope, resolver);
+        }
+    }
+}
+
+fn resolve_key(json: &mut JsonValue, key: &str, scope: &Scope, resolver: &Resolver) {
+    let canonical = match json[key].as_str() {
+        Some(ty) => resolver.canonical_type(ty, scope),
+        None => return,
+    };
+    if json[key] != canonical.as_str() {
+        json[format!("resolved_{}", key)] = JsonValue::String(canonical);
+    }
+}
+
+/// Add the list of the dependencies exposed in the API (the public dependencies), with the
+/// items which expose them, in the `public_dependencies` key.
+///
+/// Dependencies are exposed by the types of public signatures, fields, trait bounds and
+/// impls, and by `pub use` declarations.
+pub fn public_dependencies(json: &mut JsonValue, edition: Edition) {
+    let resolver = Resolver::new(json, edition);
+    let mut dependencies = BTreeMap::new();
+    for module in json["modules"].members() {
+        let path = module["module"].as_str().unwrap_or("crate");
+        module_dependencies(module, path, &resolver, &mut dependencies);
+    }
+    let mut js = JsonValue::new_object();
+    for (krate, items) in dependencies {
+        js[krate] = JsonValue::Array(items.into_iter().map(JsonValue::String).collect());
+    }
+    json["public_dependencies"] = js;
+}
+
+fn module_dependencies(
+    module: &JsonValue,
+    path: &str,
+    resolver: &Resolver,
+    dependencies: &mut BTreeMap<String, BTreeSet<String>>,
+) {
+    let scope = Scope {
+        json: &module["scope"],
+        module: path,
+    };
+    for item in module["items"].members() {
+        if item["type"] == "mod" {
+            let name = item["name"].as_str().unwrap_or("");
+            module_dependencies(item, &format!("{}::{}", path, name), resolver, dependencies);
+            continue;
+        }
+        let mut crates = BTreeSet::new();
+        if item["type"] == "extern_crate" {
+            // `pub extern crate`
+            crates.extend(item["crate"].as_str().map(|c| c.to_owned()));
+        } else if item["type"] == "usetree" {
+            // `pub use`
+            for use_path in use_paths(item, "") {
+                crates.extend(resolver.crate_of_use_path(&use_path, &scope));
+            }
+        } else {
+            for ty in item_types(item) {
+                for (start, end) in type_paths(ty) {
+                    crates.extend(resolver.crate_of_type_path(&ty[start..end], &scope));
+                }
+            }
+        }
+        for krate in crates {
+            if STD_CRATES.contains(&krate.as_str()) {
+                continue;
+            }
+            dependencies
+                .entry(krate)
+                .or_insert_with(BTreeSet::new)
+                .insert(item_path(item, path));
+        }
+    }
+}
+
+/// Returns the path of an item, like `crate::a::Foo` or `crate::a::<impl Trait for Foo>`.
+fn item_path(item: &JsonValue, path: &str) -> String {
+    match item["type"].as_str() {
+        Some("impl") => match item["trait"].as_str() {
+            Some(t) if !t.is_empty() => format!("{}::<impl {} for {}>", path, t, item["impl_type"]),
+            _ => format!("{}::<impl {}>", path, item["impl_type"]),
+        },
+        Some("usetree") => format!("{}::{{use {}}}", path, item["path"]),
+        _ => format!("{}::{}", path, item["name"]),
+    }
+}
+
+/// Returns the full paths imported by a use tree.
+fn use_paths(usetree: &JsonValue, prefix: &str) -> Vec<String> {
+    let path = usetree["path"].as_str().unwrap_or("");
+    let path = match (prefix.is_empty(), path.is_empty()) {
+        (true, _) => path.to_owned(),
+        (false, true) => prefix.to_owned(),
+        (false, false) => format!("{}::{}", prefix, path),
+    };
+    if usetree["kind"] == "nested" {
+        usetree["usetree"]
+            .members()
+            .flat_map(|u| use_paths(u, &path))
+            .collect()
+    } else {
+        vec![path]
+    }
+}
+
+/// Returns the types (and trait bounds) of the public parts of an item, and of the items it
+/// contains (for traits and impls).
+fn item_types(item: &JsonValue) -> Vec<&str> {
+    let mut types = Vec::new();
+    for key in &[
+        "output",
+        "subtype",
+        "generics",
+        "where",
+        "impl_type",
+        "trait",
+    ] {
+        types.extend(item[*key].as_str());
+    }
+    types.extend(item["inputs"].members().filter_map(|i| i["type"].as_str()));
+    types.extend(
+        item["typarambounds"]
+            .members()
+            .filter_map(|b| b["trait_ref"].as_str()),
+    );
+    if item["type"] == "enum" {
+        for variant in item["fields"].members() {
+            types.extend(
+                variant["fields"]
+                    .members()
+                    .filter_map(|f| f["type"].as_str()),
+            );
+        }
+    } else {
+        types.extend(
+            item["fields"]
+                .members()
+                .filter(|f| f["visibility"] == "public")
+                .filter_map(|f| f["type"].as_str()),
+        );
+    }
+    for child in item["items"].members() {
+        types.extend(item_types(child));
+    }
+    types
+}
+
+/// Returns the positions of the paths used in a type, like `bytes::Bytes` and `Vec` in
+/// `Vec<bytes::Bytes>`.
+fn type_paths(ty: &str) -> Vec<(usize, usize)> {
+    let mut paths = Vec::new();
+    let chars: Vec<(usize, char)> = ty.char_indices().collect();
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let at_separator = |i: usize| {
+        i + 2 < chars.len()
+            && chars[i].1 == ':'
+            && chars[i + 1].1 == ':'
+            && is_ident(chars[i + 2].1)
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        if c == '"' {
+            // ABI strings
+            i += 1;
+            while i < chars.len() && chars[i].1 != '"' {
+                i += 1;
+            }
+            i += 1;
+        } else if c == '\'' || c.is_numeric() {
+            // lifetimes and array lengths
+            i += 1;
+            while i < chars.len() && is_ident(chars[i].1) {
+                i += 1;
+            }
+        } else if is_ident(c) || (at_separator(i) && (i == 0 || !is_path_end(chars[i - 1].1))) {
+            let start = chars[i].0;
+            if c == ':' {
+                i += 2;
+            }
+            loop {
+                while i < chars.len() && is_ident(chars[i].1) {
+                    i += 1;
+                }
+                if at_separator(i) {
+                    i += 2;
+                } else {
+                    break;
+                }
+            }
+            let end = if i < chars.len() {
+                chars[i].0
+            } else {
+                ty.len()
+            };
+            paths.push((start, end));
+        } else {
+            i += 1;
+        }
+    }
+    paths
+}
+
+/// `<T as Trait>::Item` is not an absolute path.
+fn is_path_end(c: char) -> bool {
+    c == '>' || c.is_alphanumeric() || c == '_'
+}
+
+/// Remove the scopes of the modules.
+pub fn remove_scopes(json: &mut JsonValue) {
+    for module in json["modules"].members_mut() {
+        remove_module_scopes(module);
+    }
+}
+
+fn remove_module_scopes(module: &mut JsonValue) {
+    module.remove("scope");
+    for item in module["items"].members_mut() {
+        if item["type"] == "mod" {
+            remove_module_scopes(item);
+        }
+    }
+}
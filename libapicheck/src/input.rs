@@ -1,29 +1,48 @@
 use crate::DirectoryOwnership;
 use crate::FileName;
 use rustc_span::symbol;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// The input to rustfmt.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Input {
     /// A file on the filesystem.
     File(PathBuf),
-    /// A UTF-8 string, in many cases from stdin.
-    Text(String),
+    /// A UTF-8 string, in many cases from stdin, and the path of the file it stands for
+    /// (used to resolve `mod` declarations), if known.
+    Text(String, Option<PathBuf>),
+    /// A crate directory, the root file is found through its manifest.
+    Directory(PathBuf),
+    /// A packaged crate (`.crate` or `.tar.gz` archive), unpacked into a temporary directory.
+    Archive(PathBuf),
 }
 
 impl Input {
+    /// Returns the input for `path`, depending on whether it is a directory, an archive or a file.
+    pub fn from_path(path: PathBuf) -> Input {
+        if path.is_dir() {
+            Input::Directory(path)
+        } else if is_archive(&path) {
+            Input::Archive(path)
+        } else {
+            Input::File(path)
+        }
+    }
+
     pub(crate) fn file_name(&self) -> FileName {
         match *self {
-            Input::File(ref file) => FileName::Real(file.clone()),
-            Input::Text(..) => FileName::Stdin,
+            Input::File(ref file) | Input::Directory(ref file) | Input::Archive(ref file) => {
+                FileName::Real(file.clone())
+            }
+            Input::Text(_, Some(ref file)) => FileName::Real(file.clone()),
+            Input::Text(_, None) => FileName::Stdin,
         }
     }
 
     pub(crate) fn to_directory_ownership(&self, recursive: bool) -> Option<DirectoryOwnership> {
         match self {
             // On recursive mode, we assume that input is the root file.
-            Input::File(..) if recursive => None,
+            Input::File(..) | Input::Text(_, Some(_)) if recursive => None,
             Input::File(ref file) => {
                 // If there exists a directory with the same name as an input,
                 // then the input should be parsed as a sub module.
@@ -36,7 +55,12 @@ impl Input {
                     None
                 }
             }
-            Input::Text(..) => None,
+            Input::Text(..) | Input::Directory(..) | Input::Archive(..) => None,
         }
     }
 }
+
+fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".crate") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
@@ -3,11 +3,17 @@
 extern crate json;
 use json::JsonValue;
 
-use rustc_ast::ast;
+use rustc_ast::{ast, ptr};
 use rustc_ast_pretty::pprust::{self, PrintState};
-use rustc_span::symbol;
+use rustc_span::source_map::edition::Edition;
+use rustc_span::{symbol, Span};
 
+use crate::attr::repr_of_attrs;
+use crate::cfg::{is_cfg_enabled, CfgExpr};
 use crate::context::Context;
+use crate::macros::MAX_EXPANSION_DEPTH;
+use crate::modules::is_cfg_if;
+use crate::syntux::parser::Parser;
 
 fn fun_decl_to_json(ident: &symbol::Ident, fndecl: &ast::FnDecl) -> JsonValue {
     let mut fun_js = JsonValue::new_array();
@@ -127,12 +133,39 @@ fn fun_to_json(
         ast::Async::No => false,
     };
     fun_js["async"] = json::JsonValue::Boolean(b);
+    js_add_opaque_return(&mut fun_js, fndecl, b);
     //
     js_add_generics(&mut fun_js, generics, context);
     //
     fun_js
 }
 
+/// Add the effective return type of functions returning an opaque type (`impl Future<Output = T>`
+/// for `async fn`, `impl Trait`), and the bounds it promises.
+fn js_add_opaque_return(js: &mut JsonValue, fndecl: &ast::FnDecl, is_async: bool) {
+    let bounds: Vec<String> = if is_async {
+        let output = match js["output"].as_str() {
+            Some("") | None => "()".to_owned(),
+            Some(output) => output.to_owned(),
+        };
+        vec![format!("Future<Output = {}>", output)]
+    } else {
+        match &fndecl.output {
+            ast::FnRetTy::Ty(ty) => match &ty.kind {
+                ast::TyKind::ImplTrait(_, bounds) => bounds
+                    .iter()
+                    .map(|bound| pprust::bounds_to_string(std::slice::from_ref(bound)))
+                    .collect(),
+                _ => return,
+            },
+            ast::FnRetTy::Default(_) => return,
+        }
+    };
+    js["return_type"] = json::JsonValue::String(format!("impl {}", bounds.join(" + ")));
+    js["return_bounds"] =
+        json::JsonValue::Array(bounds.into_iter().map(json::JsonValue::String).collect());
+}
+
 fn trait_to_json(
     ident: &symbol::Ident,
     traitkind: &ast::TraitKind,
@@ -187,6 +220,9 @@ fn trait_to_json(
 }
 
 fn check_traititem(it: &ast::AssocItem, context: &Context) -> Option<JsonValue> {
+    if !is_cfg_enabled(&it.attrs, context.config) {
+        return None;
+    }
     let mut js = json::JsonValue::new_object();
     js["name"] = json::JsonValue::String(format!("{}", &it.ident));
     match &it.kind {
@@ -212,18 +248,23 @@ fn check_traititem(it: &ast::AssocItem, context: &Context) -> Option<JsonValue>
             }
             js_add_generics(&mut js, generics, context);
         }
-        ast::AssocItemKind::MacCall(ref _mac) => {
-            js["type"] = json::JsonValue::String("macro".to_owned());
-            // XXX macro invocation ?
+        ast::AssocItemKind::MacCall(ref mac) => {
+            // shadow previous js
+            js = mac_call_to_json(mac, it.span, context);
         }
     }
     // generics // XXX moved to Fn and TyAlias variants
     // js_add_generics(&mut js, &it.generics);
     //
+    js_add_location(&mut js, it.span, context);
     Some(js)
 }
 
-fn structfield_to_json(ident: &Option<symbol::Ident>, field: &ast::FieldDef) -> JsonValue {
+fn structfield_to_json(
+    ident: &Option<symbol::Ident>,
+    field: &ast::FieldDef,
+    context: &Context,
+) -> JsonValue {
     let mut js = JsonValue::new_array();
     //
     let name = match ident {
@@ -242,6 +283,7 @@ fn structfield_to_json(ident: &Option<symbol::Ident>, field: &ast::FieldDef) ->
     js["visibility"] = json::JsonValue::String(s.to_owned());
     // attrs ?
     //
+    js_add_location(&mut js, field.span, context);
     js
 }
 
@@ -258,7 +300,8 @@ fn variantdata_to_json(
     let v = match variantdata {
         ast::VariantData::Struct(ref fields, _) | ast::VariantData::Tuple(ref fields, _) => fields
             .iter()
-            .map(|ref f| structfield_to_json(&f.ident, &f))
+            .filter(|f| is_cfg_enabled(&f.attrs, context.config))
+            .map(|ref f| structfield_to_json(&f.ident, &f, context))
             .collect(),
         // ast::VariantData::Tuple(ref _fields, _id) => vec![],
         ast::VariantData::Unit(_) => vec![],
@@ -285,19 +328,33 @@ fn enum_to_json(
     let v = enumdef
         .variants
         .iter()
+        .filter(|variant| is_cfg_enabled(&variant.attrs, context.config))
         .map(|ref variant| {
-            variantdata_to_json(
+            let mut js = variantdata_to_json(
                 &variant.ident,
                 &variant.data,
                 generics, /* XXX */
                 context,
-            )
+            );
+            if let Some(ref disr) = variant.disr_expr {
+                js["discriminant"] = json::JsonValue::String(pprust::expr_to_string(&disr.value));
+            }
+            js_add_location(&mut js, variant.span, context);
+            js
         })
         .collect();
     js["fields"] = json::JsonValue::Array(v);
     js
 }
 
+/// Add the `#[repr(...)]` hints of a type, which make its layout part of the ABI.
+fn js_add_repr(js: &mut json::JsonValue, attrs: &[ast::Attribute]) {
+    let repr = repr_of_attrs(attrs);
+    if !repr.is_empty() {
+        js["repr"] = json::JsonValue::Array(repr.into_iter().map(JsonValue::String).collect());
+    }
+}
+
 fn impl_to_json(ident: &symbol::Ident, implkind: &ast::ImplKind, context: &Context) -> JsonValue {
     let mut js = JsonValue::new_array();
     js["type"] = json::JsonValue::String("impl".to_owned());
@@ -309,7 +366,13 @@ fn impl_to_json(ident: &symbol::Ident, implkind: &ast::ImplKind, context: &Conte
     // trait being implemented
     let (self_impl, thetrait) = match &implkind.of_trait {
         None => (true, "".to_owned()),
-        Some(ref tref) => (false, pprust::path_to_string(&tref.path)),
+        Some(ref tref) => match implkind.polarity {
+            // `impl !Send for T`
+            ast::ImplPolarity::Negative(_) => {
+                (false, format!("!{}", pprust::path_to_string(&tref.path)))
+            }
+            ast::ImplPolarity::Positive => (false, pprust::path_to_string(&tref.path)),
+        },
     };
     js["trait"] = json::JsonValue::String(thetrait);
     //
@@ -331,7 +394,9 @@ fn impl_to_json(ident: &symbol::Ident, implkind: &ast::ImplKind, context: &Conte
         .items
         .iter()
         .filter_map(|ref it| {
-            if self_impl && !it.vis.kind.is_pub() {
+            // macro invocations may define public items
+            let is_mac_call = matches!(it.kind, ast::AssocItemKind::MacCall(..));
+            if self_impl && !it.vis.kind.is_pub() && !is_mac_call {
                 None
             } else {
                 check_implitem(it, context)
@@ -344,6 +409,9 @@ fn impl_to_json(ident: &symbol::Ident, implkind: &ast::ImplKind, context: &Conte
 }
 
 fn check_implitem(it: &ast::AssocItem, context: &Context) -> Option<JsonValue> {
+    if !is_cfg_enabled(&it.attrs, context.config) {
+        return None;
+    }
     let mut js = json::JsonValue::new_object();
     js["name"] = json::JsonValue::String(format!("{}", &it.ident));
     match &it.kind {
@@ -375,9 +443,9 @@ fn check_implitem(it: &ast::AssocItem, context: &Context) -> Option<JsonValue> {
         //     js["type"] = json::JsonValue::from("existential");
         //     js["bounds"] = json::JsonValue::String(pprust::bounds_to_string(&bounds));
         // },
-        ast::AssocItemKind::MacCall(ref _mac) => {
-            js["type"] = json::JsonValue::String("macro".to_owned());
-            // XXX macro invocation ?
+        ast::AssocItemKind::MacCall(ref mac) => {
+            // shadow previous js
+            js = mac_call_to_json(mac, it.span, context);
         }
     }
     let s = match &it.vis.kind {
@@ -389,6 +457,7 @@ fn check_implitem(it: &ast::AssocItem, context: &Context) -> Option<JsonValue> {
     // generics // XXX moved to AssocItemKind variants
     // js_add_generics(&mut js, &it.generics);
     //
+    js_add_location(&mut js, it.span, context);
     Some(js)
 }
 
@@ -409,11 +478,15 @@ fn mod_to_json(
     let mut js = json::JsonValue::new_object();
     js["name"] = json::JsonValue::String(format!("{}", ident));
     js["type"] = json::JsonValue::String("mod".to_owned());
-    let v: Vec<_> = items
-        .iter()
-        .filter_map(|ref it| check_item(it, context))
-        .collect();
+    let v = check_items(items, context);
     js["items"] = json::JsonValue::Array(v);
+    let imports = local_imports_to_json(items, context.config.edition);
+    if !imports.is_empty() {
+        js["imports"] = imports;
+    }
+    if context.config.needs_scopes() {
+        js["scope"] = scope_to_json(items);
+    }
     let b = match unsafety {
         ast::Unsafe::Yes(_) => true,
         ast::Unsafe::No => false,
@@ -453,6 +526,134 @@ fn usetree_to_json(ident: Option<symbol::Ident>, usetree: &ast::UseTree) -> Json
     js
 }
 
+/// Returns the names imported (`use`, `extern crate`) and defined by the items of a module,
+/// including private items, to resolve the paths used in the types of the module, and the
+/// field types of its structs, enums and unions.
+///
+/// The scope is removed from the output once the paths are resolved.
+pub(crate) fn scope_to_json(items: &[ptr::P<ast::Item>]) -> JsonValue {
+    let mut imports = json::JsonValue::new_object();
+    let mut globs = Vec::new();
+    let mut names = Vec::new();
+    let mut types = json::JsonValue::new_object();
+    for it in items {
+        let fields: Option<Vec<&ast::FieldDef>> = match &it.kind {
+            ast::ItemKind::Struct(ref variantdata, _)
+            | ast::ItemKind::Union(ref variantdata, _) => {
+                Some(variantdata.fields().iter().collect())
+            }
+            ast::ItemKind::Enum(ref enumdef, _) => Some(
+                enumdef
+                    .variants
+                    .iter()
+                    .flat_map(|variant| variant.data.fields())
+                    .collect(),
+            ),
+            _ => None,
+        };
+        if let Some(fields) = fields {
+            let v = fields
+                .iter()
+                .map(|field| json::JsonValue::String(pprust::ty_to_string(&field.ty)))
+                .collect();
+            types[format!("{}", it.ident)] = json::JsonValue::Array(v);
+        }
+        match &it.kind {
+            ast::ItemKind::Use(ref usetree) => {
+                add_use_imports(usetree, "", &mut imports, &mut globs);
+            }
+            ast::ItemKind::ExternCrate(ref orig_name) => {
+                // extern crates are absolute paths
+                let krate = orig_name.unwrap_or(it.ident.name);
+                imports[format!("{}", it.ident)] = json::JsonValue::String(format!("::{}", krate));
+            }
+            ast::ItemKind::Impl(..)
+            | ast::ItemKind::ForeignMod(..)
+            | ast::ItemKind::GlobalAsm(..)
+            | ast::ItemKind::MacCall(..)
+            | ast::ItemKind::MacroDef(..) => (),
+            _ => names.push(json::JsonValue::String(format!("{}", it.ident))),
+        }
+    }
+    let mut js = json::JsonValue::new_object();
+    js["imports"] = imports;
+    js["globs"] = json::JsonValue::Array(globs);
+    js["names"] = json::JsonValue::Array(names);
+    js["types"] = types;
+    js
+}
+
+/// Returns the names imported from the crate by the `use` declarations of a module, including
+/// private ones, with their paths (`{"Result": "crate::error::Result"}`), so that `apidiff`
+/// can find the type aliases they refer to.
+///
+/// Paths are relative to the crate root in the 2015 edition. In later editions, they start
+/// with `crate`, `self`, `super` or the name of a child module.
+pub(crate) fn local_imports_to_json(items: &[ptr::P<ast::Item>], edition: Edition) -> JsonValue {
+    let mut imports = json::JsonValue::new_object();
+    let mut globs = Vec::new();
+    let mut child_modules = Vec::new();
+    for it in items {
+        match it.kind {
+            ast::ItemKind::Use(ref usetree) => {
+                add_use_imports(usetree, "", &mut imports, &mut globs)
+            }
+            ast::ItemKind::Mod(..) => child_modules.push(format!("{}", it.ident)),
+            _ => (),
+        }
+    }
+    let mut local = json::JsonValue::new_object();
+    for (name, path) in imports.entries() {
+        let path = path.as_str().unwrap_or("");
+        let first = path.split("::").next().unwrap_or("");
+        let is_local = match first {
+            "crate" | "self" | "super" => true,
+            // `::name` is an extern crate
+            "" => false,
+            _ => edition == Edition::Edition2015 || child_modules.iter().any(|m| m == first),
+        };
+        if is_local {
+            local[name] = json::JsonValue::String(path.to_owned());
+        }
+    }
+    local
+}
+
+/// Add the names imported by a use tree, with their full paths.
+fn add_use_imports(
+    usetree: &ast::UseTree,
+    prefix: &str,
+    imports: &mut JsonValue,
+    globs: &mut Vec<JsonValue>,
+) {
+    let path = pprust::path_to_string(&usetree.prefix);
+    let path = match (prefix.is_empty(), path.is_empty()) {
+        (true, _) => path,
+        (false, true) => prefix.to_owned(),
+        (false, false) => format!("{}::{}", prefix, path),
+    };
+    match usetree.kind {
+        ast::UseTreeKind::Simple(rename, _, _) => {
+            // `use a::b::{self}` imports `b`
+            let path = path.trim_end_matches("::self").to_owned();
+            let name = match rename {
+                Some(ident) => format!("{}", ident),
+                None => path.rsplit("::").next().unwrap_or("").to_owned(),
+            };
+            // `use a as _;` does not import a name
+            if !name.is_empty() && name != "_" {
+                imports[name] = json::JsonValue::String(path);
+            }
+        }
+        ast::UseTreeKind::Nested(ref nested) => {
+            for (u, _) in nested {
+                add_use_imports(u, &path, imports, globs);
+            }
+        }
+        ast::UseTreeKind::Glob => globs.push(json::JsonValue::String(path)),
+    }
+}
+
 // fn existential_to_json(ident: &ast::Ident, bounds: &ast::GenericBounds, generics: &ast::Generics) -> JsonValue {
 //     let mut js = JsonValue::new_array();
 //     //
@@ -467,6 +668,133 @@ fn usetree_to_json(ident: Option<symbol::Ident>, usetree: &ast::UseTree) -> Json
 //     js
 // }
 
+/// Check a list of items, expanding `cfg_if!` invocations and crate-local macros.
+pub(crate) fn check_items(items: &[ptr::P<ast::Item>], context: &Context) -> Vec<JsonValue> {
+    check_items_at_depth(items, context, 0)
+}
+
+fn check_items_at_depth(
+    items: &[ptr::P<ast::Item>],
+    context: &Context,
+    depth: usize,
+) -> Vec<JsonValue> {
+    let mut v = Vec::new();
+    for it in items {
+        if is_cfg_if(it) {
+            v.extend(check_cfg_if(it, None, context, depth));
+        } else if let ast::ItemKind::MacCall(ref mac) = it.kind {
+            if context.config.expand_macros {
+                v.extend(expand_macro(it, mac, context, depth));
+            } else {
+                v.push(mac_call_to_json(mac, it.span, context));
+            }
+        } else if let Some(js) = check_item(it, context) {
+            v.push(js);
+        }
+    }
+    v
+}
+
+/// Expand an item-position macro invocation, and check the resulting items.
+fn expand_macro(
+    it: &ast::Item,
+    mac: &ast::MacCall,
+    context: &Context,
+    depth: usize,
+) -> Vec<JsonValue> {
+    let (macros, parse_sess) = match (context.macros(), context.parse_sess()) {
+        (Some(macros), Some(parse_sess)) => (macros, parse_sess),
+        _ => return vec![],
+    };
+    let result = if depth >= MAX_EXPANSION_DEPTH {
+        Err("recursion limit reached".to_owned())
+    } else {
+        macros.expand(mac, it.span)
+    };
+    match result {
+        Ok(items) => check_items_at_depth(&items, context, depth + 1),
+        Err(e) => {
+            context.add_unexpanded(format!(
+                "{}! at {}: {}",
+                pprust::path_to_string(&mac.path),
+                parse_sess.span_to_debug_info(it.span),
+                e
+            ));
+            vec![mac_call_to_json(mac, it.span, context)]
+        }
+    }
+}
+
+/// Describe an unexpanded macro invocation.
+///
+/// The items it defines are unknown, so the invocation is described by the macro path, its
+/// location, and a fingerprint of its tokens.
+fn mac_call_to_json(mac: &ast::MacCall, span: Span, context: &Context) -> JsonValue {
+    let mut js = json::JsonValue::new_object();
+    let path = pprust::path_to_string(&mac.path);
+    js["type"] = json::JsonValue::String("macro".to_owned());
+    js["name"] = json::JsonValue::String(format!("{}!", path));
+    js["path"] = json::JsonValue::String(path);
+    js_add_location(&mut js, span, context);
+    let tokens = pprust::tts_to_string(&mac.args.inner_tokens());
+    js["fingerprint"] = json::JsonValue::String(format!("{:016x}", fnv1a(tokens.as_bytes())));
+    js
+}
+
+/// 64-bit FNV-1a hash, stable across platforms and compiler versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Check the items declared in the branches of a `cfg_if!` invocation.
+///
+/// The condition of each branch is stored in the `cfg` key of the items. `depth` is the
+/// macro expansion depth of the invocation.
+fn check_cfg_if(
+    it: &ast::Item,
+    outer_cfg: Option<CfgExpr>,
+    context: &Context,
+    depth: usize,
+) -> Vec<JsonValue> {
+    let (mac, parse_sess) = match (&it.kind, context.parse_sess()) {
+        (ast::ItemKind::MacCall(ref mac), Some(parse_sess)) => (mac, parse_sess),
+        _ => return vec![],
+    };
+    let branch_items = match Parser::parse_cfg_if(parse_sess, mac) {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("{}", e);
+            return vec![];
+        }
+    };
+    let mut v = Vec::new();
+    for branch_item in branch_items {
+        let cfg = CfgExpr::and(outer_cfg.clone(), branch_item.cfg);
+        if let (Some(ref cfg_set), Some(ref cfg)) = (&context.config.cfg, &cfg) {
+            if !cfg.eval(cfg_set) {
+                continue;
+            }
+        }
+        if is_cfg_if(&branch_item.item) {
+            v.extend(check_cfg_if(&branch_item.item, cfg, context, depth));
+            continue;
+        }
+        // the item may be a macro invocation, to expand or report
+        for mut js in check_items_at_depth(&[ptr::P(branch_item.item)], context, depth) {
+            if let Some(ref cfg) = cfg {
+                js["cfg"] = json::JsonValue::String(cfg.to_string());
+            }
+            v.push(js);
+        }
+    }
+    v
+}
+
 pub fn check_item(it: &ast::Item, context: &Context) -> Option<JsonValue> {
     // handle some specific item types
     match &it.kind {
@@ -485,6 +813,12 @@ pub fn check_item(it: &ast::Item, context: &Context) -> Option<JsonValue> {
             }
         },
     }
+    if !is_cfg_enabled(&it.attrs, context.config) {
+        if context.config.debug > 0 {
+            println!("skipping item '{}', disabled by cfg", it.ident);
+        }
+        return None;
+    }
     if context.config.debug > 3 {
         println!("check_item, item {:#?}", it);
     }
@@ -496,6 +830,18 @@ pub fn check_item(it: &ast::Item, context: &Context) -> Option<JsonValue> {
             let js = usetree_to_json(Some(it.ident), usetree);
             Some(js)
         }
+        ast::ItemKind::ExternCrate(ref orig_name) => {
+            let mut js = json::JsonValue::new_object();
+            js["name"] = json::JsonValue::String(format!("{}", &it.ident));
+            js["type"] = json::JsonValue::String("extern_crate".to_owned());
+            // `extern crate foo as bar;` is named `bar`
+            let krate = match orig_name {
+                Some(name) => format!("{}", name),
+                None => format!("{}", &it.ident),
+            };
+            js["crate"] = json::JsonValue::String(krate);
+            Some(js)
+        }
         ast::ItemKind::Const(_, ty, _) => {
             let mut js = json::JsonValue::new_object();
             js["name"] = json::JsonValue::String(format!("{}", &it.ident));
@@ -540,6 +886,7 @@ pub fn check_item(it: &ast::Item, context: &Context) -> Option<JsonValue> {
             }
             let mut js = variantdata_to_json(&it.ident, variantdata, generics, context);
             js["type"] = json::JsonValue::String("struct".to_owned());
+            js_add_repr(&mut js, &it.attrs);
             if context.config.debug > 0 {
                 println!("json: {}", js.pretty(2));
             }
@@ -549,7 +896,8 @@ pub fn check_item(it: &ast::Item, context: &Context) -> Option<JsonValue> {
             if context.config.debug > 2 {
                 println!("Early pass, enum {:#?} {:#?}", enumdef, generics);
             }
-            let js = enum_to_json(&it.ident, enumdef, generics, context);
+            let mut js = enum_to_json(&it.ident, enumdef, generics, context);
+            js_add_repr(&mut js, &it.attrs);
             if context.config.debug > 0 {
                 println!("json: {}", js.pretty(2));
             }
@@ -562,6 +910,7 @@ pub fn check_item(it: &ast::Item, context: &Context) -> Option<JsonValue> {
             }
             let mut js = variantdata_to_json(&it.ident, variantdata, generics, context);
             js["type"] = json::JsonValue::String("union".to_owned());
+            js_add_repr(&mut js, &it.attrs);
             if context.config.debug > 0 {
                 println!("json: {}", js.pretty(2));
             }
@@ -614,10 +963,24 @@ pub fn check_item(it: &ast::Item, context: &Context) -> Option<JsonValue> {
             .map(|ref attr| json::JsonValue::String(pprust::attribute_to_string(attr)))
             .collect();
         js["attrs"] = json::JsonValue::Array(v);
+        js_add_location(&mut js, it.span, context);
         js
     })
 }
 
+/// Add the source location (file, first line and column, last line) of `span`.
+fn js_add_location(js: &mut json::JsonValue, span: Span, context: &Context) {
+    if let Some(parse_sess) = context.parse_sess() {
+        let location = parse_sess.span_to_location(span);
+        let mut js_loc = json::JsonValue::new_object();
+        js_loc["file"] = json::JsonValue::String(location.file);
+        js_loc["line"] = location.line.into();
+        js_loc["column"] = location.column.into();
+        js_loc["end_line"] = location.end_line.into();
+        js["location"] = js_loc;
+    }
+}
+
 fn js_add_generics(js: &mut json::JsonValue, generics: &ast::Generics, context: &Context) {
     // generics
     let s_gen = context
@@ -637,6 +1000,8 @@ pub(crate) fn is_mod_decl(item: &ast::Item) -> bool {
         ast::ItemKind::Mod(_, ast::ModKind::Loaded(_, _, span)) => {
             span.hi() != item.span.hi()
         }
+        // `mod foo;` is not loaded by the parser
+        ast::ItemKind::Mod(_, ast::ModKind::Unloaded) => true,
         _ => false,
     }
 }
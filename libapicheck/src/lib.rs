@@ -1,39 +1,64 @@
+extern crate apicheck_common;
+extern crate flate2;
+extern crate getopts;
+extern crate glob;
 extern crate ignore;
 extern crate json;
+extern crate tar;
+extern crate tempfile;
 extern crate term;
 extern crate thiserror;
+extern crate toml;
 
 extern crate rustc_ast;
 extern crate rustc_ast_pretty;
 extern crate rustc_data_structures;
 extern crate rustc_errors;
 extern crate rustc_expand;
+extern crate rustc_feature;
 extern crate rustc_parse;
 extern crate rustc_session;
 extern crate rustc_span;
 
 use std::convert::From;
 use std::fs::File;
-use std::io;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
+use json::JsonValue;
 use rustc_errors::DiagnosticBuilder;
 
 pub(crate) mod attr;
+pub(crate) mod autotraits;
+pub(crate) mod canonical;
+pub(crate) mod features;
+pub(crate) mod ffi;
 pub(crate) mod ignore_path;
+pub(crate) mod imports;
 mod input;
 pub(crate) mod items;
+pub(crate) mod macros;
 pub(crate) mod modules;
 pub(crate) mod process;
 pub(crate) mod syntux;
+pub(crate) mod unsafety;
 
+pub mod cfg;
 pub mod config;
 pub mod context;
+pub mod git;
+pub mod manifest;
+pub mod options;
+pub mod registry;
 use crate::context::Context;
-pub use config::{Config, FileName};
+pub use apicheck_common::filter;
+pub use config::{Config, FileName, OutputFormat};
 use modules::ModuleResolutionError;
 
 pub use input::Input;
 pub use items::check_item;
+use macros::MacroDefs;
+use manifest::Manifest;
 use process::create_json_from_crate;
 use syntux::parser::{DirectoryOwnership, Parser};
 pub(crate) use syntux::session::ParseSess;
@@ -106,6 +131,30 @@ pub enum ErrorKind {
     /// Invalid glob pattern in `ignore` configuration option.
     #[error("Invalid glob pattern found in ignore list: {0}")]
     InvalidGlobPattern(ignore::Error),
+    /// The Cargo manifest could not be read or is invalid.
+    #[error("invalid manifest {0:?}: {1}")]
+    InvalidManifest(PathBuf, String),
+    /// The package was not found in the workspace.
+    #[error("package `{0}` not found in workspace")]
+    PackageNotFound(String),
+    /// The manifest is a virtual manifest, and no package was selected.
+    #[error("{0:?} is a virtual manifest, select a package with --package (one of: {1})")]
+    VirtualManifest(PathBuf, String),
+    /// A git command failed.
+    #[error("git error: {0}")]
+    GitError(String),
+    /// The crate was not found in the local Cargo registry.
+    #[error("crate {0} not found in the local registry")]
+    CrateNotFound(String),
+    /// The edition is not supported.
+    #[error("invalid edition: {0}")]
+    InvalidEdition(String),
+    /// The configuration file is invalid.
+    #[error("invalid configuration file {0:?}: {1}")]
+    InvalidConfig(PathBuf, String),
+    /// A feature matrix was requested without a manifest declaring the features.
+    #[error("--feature-matrix requires a Cargo.toml (see --manifest-path)")]
+    FeatureMatrixWithoutManifest,
 }
 
 // impl ErrorKind {
@@ -124,10 +173,112 @@ impl From<io::Error> for ErrorKind {
 }
 
 pub fn process_file(input: Input, config: &Config) -> Result<(), ErrorKind> {
-    rustc_span::with_session_globals(config.edition, || process_project(input, &config))
+    let json = extract_api(input, config)?;
+    write_json(&json, &config.output, config.output_format).expect("writing JSON failed");
+    Ok(())
+}
+
+/// Extract the API description of `input`, without writing it.
+pub fn extract_api(input: Input, config: &Config) -> Result<JsonValue, ErrorKind> {
+    let mut json = match input {
+        Input::Directory(ref dir) => extract_crate_dir(dir, config)?,
+        Input::Archive(ref path) => {
+            let source = registry::unpack_crate(path)?;
+            extract_crate_dir(&source.dir, config)?
+        }
+        Input::File(..) | Input::Text(..) => extract_source(input, config)?,
+    };
+    if config.resolve_paths {
+        // the canonical types are only comparable with descriptions which also have them
+        json["resolve_paths"] = true.into();
+    }
+    if config.ffi {
+        ffi::ffi_surface(&mut json);
+    }
+    if config.unsafe_surface {
+        unsafety::unsafe_surface(&mut json);
+    }
+    if config.canonical {
+        canonical::canonicalize(&mut json);
+    }
+    Ok(json)
+}
+
+/// Extract the API of a source file, before the passes selecting a part of the API.
+///
+/// The edition is recorded when it was set explicitly or read from a manifest, so that
+/// `apidiff` resolves paths as in the crate.
+fn extract_source(input: Input, config: &Config) -> Result<JsonValue, ErrorKind> {
+    let mut json =
+        rustc_span::with_session_globals(config.edition, || match config.feature_sets {
+            Some(ref feature_sets) => {
+                features::extract_feature_matrix(&input, config, feature_sets)
+            }
+            None => process_project(input, config),
+        })?;
+    if config.edition_is_known() {
+        json["edition"] = config.edition.to_string().into();
+    }
+    Ok(json)
+}
+
+/// Extract the API of the library of the crate in `dir`, with the edition declared in its
+/// manifest unless one was given explicitly. File paths are relative to `dir`.
+fn extract_crate_dir(dir: &Path, config: &Config) -> Result<JsonValue, ErrorKind> {
+    let manifest = Manifest::from_path(&dir.join("Cargo.toml"))?;
+    let mut config = config.clone();
+    config.set_default_edition(manifest.edition()?);
+    config.resolve_feature_sets(Some(&manifest))?;
+    let mut json = extract_source(Input::File(manifest.lib_root()?), &config)?;
+    json["crate"] = manifest.crate_to_json();
+    relative_paths(&mut json, dir);
+    Ok(json)
+}
+
+/// Make the paths of the modules and of the source locations relative to `dir`, in the
+/// description of a crate or of a workspace.
+pub fn relative_paths(json: &mut JsonValue, dir: &Path) {
+    if json.has_key("crates") {
+        for (_, krate) in json["crates"].entries_mut() {
+            relative_paths(krate, dir);
+        }
+    }
+    for module in json["modules"].members_mut() {
+        let path = module["path"].as_str().map(|p| relative_path(p, dir));
+        if let Some(path) = path {
+            module["path"] = path.into();
+        }
+        relative_locations(&mut module["items"], dir);
+    }
 }
 
-fn process_project(input: Input, config: &Config) -> Result<(), ErrorKind> {
+fn relative_path(path: &str, dir: &Path) -> String {
+    match Path::new(path).strip_prefix(dir) {
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.to_owned(),
+    }
+}
+
+/// Make the files of all source locations relative to `dir`.
+fn relative_locations(js: &mut JsonValue, dir: &Path) {
+    if js.is_object() {
+        let file = js["location"]["file"]
+            .as_str()
+            .map(|f| relative_path(f, dir));
+        if let Some(file) = file {
+            js["location"]["file"] = file.into();
+        }
+        for (_, value) in js.entries_mut() {
+            relative_locations(value, dir);
+        }
+    } else {
+        for member in js.members_mut() {
+            relative_locations(member, dir);
+        }
+    }
+}
+
+pub(crate) fn process_project(input: Input, config: &Config) -> Result<JsonValue, ErrorKind> {
     let main_file = input.file_name();
     let input_is_stdin = main_file == FileName::Stdin;
 
@@ -136,7 +287,7 @@ fn process_project(input: Input, config: &Config) -> Result<(), ErrorKind> {
     // Parse the crate.
     let recursive = true;
     let directory_ownership = input.to_directory_ownership(recursive);
-    let _original_snippet = if let Input::Text(ref str) = input {
+    let _original_snippet = if let Input::Text(ref str, _) = input {
         Some(str.to_owned())
     } else {
         None
@@ -154,6 +305,7 @@ fn process_project(input: Input, config: &Config) -> Result<(), ErrorKind> {
         &parse_session,
         directory_ownership.unwrap_or(DirectoryOwnership::UnownedViaBlock),
         !input_is_stdin && recursive,
+        config.cfg.as_ref(),
     )
     .visit_crate(&krate)?;
 
@@ -191,22 +343,62 @@ fn process_project(input: Input, config: &Config) -> Result<(), ErrorKind> {
     //     }
     // };
 
-    let context = Context::new(config);
+    let macros = if config.expand_macros {
+        MacroDefs::collect(&files, &parse_session, config.edition)
+    } else {
+        MacroDefs::default()
+    };
+    let context = Context::new(config)
+        .with_parse_sess(&parse_session)
+        .with_macros(&macros);
 
     let result = create_json_from_crate(&files, &context);
-    let json = result.expect("extracting JSON failed");
-    write_json(&json, &config.output).expect("writing JSON failed");
-    Ok(())
+    let mut json = result.expect("extracting JSON failed");
+    if config.resolve_paths {
+        imports::resolve_paths(&mut json, config.edition);
+    }
+    if config.public_dependencies {
+        imports::public_dependencies(&mut json, config.edition);
+    }
+    if config.auto_traits {
+        autotraits::auto_traits(&mut json);
+    }
+    // the analyses above need the whole crate, even the items which are not selected
+    config.filter.apply(&mut json);
+    imports::remove_scopes(&mut json);
+    if config.expand_macros {
+        let unexpanded = context.unexpanded();
+        if !unexpanded.is_empty() {
+            eprintln!(
+                "warning: {} macro invocation(s) could not be expanded:",
+                unexpanded.len()
+            );
+            for description in unexpanded {
+                eprintln!("    {}", description);
+            }
+        }
+    }
+    Ok(json)
 }
 
-fn write_json(js: &json::JsonValue, output: &FileName) -> Result<(), io::Error> {
-    match &output {
-        FileName::Stdin => panic!("Cannot output to stdin"),
-        FileName::Stdout => println!("{}", js),
-        FileName::Real(path) => {
+pub fn write_json(
+    js: &JsonValue,
+    output: &FileName,
+    format: OutputFormat,
+) -> Result<(), io::Error> {
+    match (&output, format) {
+        (FileName::Stdin, _) => panic!("Cannot output to stdin"),
+        (FileName::Stdout, OutputFormat::Compact) => println!("{}", js),
+        (FileName::Stdout, OutputFormat::Pretty) => println!("{}", js.pretty(2)),
+        (FileName::Stdout, OutputFormat::CHeader) => print!("{}", ffi::c_header(js)),
+        (FileName::Real(path), _) => {
             let file = File::create(path)?;
             let mut buf_writer = io::BufWriter::new(file);
-            js.write(&mut buf_writer)?;
+            match format {
+                OutputFormat::Compact => js.write(&mut buf_writer)?,
+                OutputFormat::Pretty => js.write_pretty(&mut buf_writer, 2)?,
+                OutputFormat::CHeader => buf_writer.write_all(ffi::c_header(js).as_bytes())?,
+            }
         }
     }
     Ok(())
@@ -0,0 +1,392 @@
+//! Expansion of declarative macros (`macro_rules!`) defined in the crate.
+//!
+//! Only item-position invocations are expanded. The macros are compiled and expanded by the
+//! `macro_rules!` implementation of rustc, with its matching rules and hygiene.
+//!
+//! Macros are looked up like rustc does for `macro_rules!`: a macro is visible after its
+//! definition, in the module defining it and its submodules (and in the parent module for
+//! `#[macro_use] mod`), and `#[macro_export]` macros can be used as `crate::name!`.
+
+use std::collections::HashMap;
+use std::io;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use rustc_ast::{ast, ptr};
+use rustc_ast_pretty::pprust;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::sync::Lrc;
+use rustc_errors::registry::Registry;
+use rustc_errors::Handler;
+use rustc_expand::base::{
+    DeriveResolutions, ExtCtxt, Indeterminate, ResolverExpand, SyntaxExtension, SyntaxExtensionKind,
+};
+use rustc_expand::compile_declarative_macro;
+use rustc_expand::expand::{AstFragment, ExpansionConfig, Invocation};
+use rustc_feature::Features;
+use rustc_session::parse::ParseSess as RawParseSess;
+use rustc_session::{config, DiagnosticOutput, Session};
+use rustc_span::edition::Edition;
+use rustc_span::hygiene::{self, AstPass, ExpnId};
+use rustc_span::symbol::{kw, sym, Ident, Symbol};
+use rustc_span::{BytePos, Span};
+
+use crate::items::is_mod_decl;
+use crate::modules::FileModMap;
+use crate::syntux::session::{silent_emitter, ParseSess};
+
+/// Maximum depth of nested macro expansions.
+pub(crate) const MAX_EXPANSION_DEPTH: usize = 64;
+
+struct MacroDef {
+    name: Symbol,
+    ext: Lrc<SyntaxExtension>,
+}
+
+/// A module, in which macros are visible after their definition.
+struct Scope {
+    path: Vec<String>,
+    lo: BytePos,
+    hi: BytePos,
+    parent: Option<usize>,
+    /// The item declaring the module in its parent, if known.
+    decl: Option<Span>,
+    macro_use: bool,
+    /// The macros defined in the module, with the position after which they are visible.
+    defs: Vec<(BytePos, usize)>,
+}
+
+/// The `macro_rules!` definitions of a crate.
+#[derive(Default)]
+pub(crate) struct MacroDefs {
+    /// The compiler session expanding the macros, created only if the crate defines macros.
+    sess: Option<Session>,
+    macros: Vec<MacroDef>,
+    /// The `#[macro_export]` macros, in the crate root.
+    exported: HashMap<Symbol, usize>,
+    scopes: Vec<Scope>,
+}
+
+impl MacroDefs {
+    /// Collect the macros defined in all modules of the crate.
+    pub(crate) fn collect(
+        files: &FileModMap,
+        parse_sess: &ParseSess,
+        edition: Edition,
+    ) -> MacroDefs {
+        let mut defs = MacroDefs::default();
+        let sess = new_session(parse_sess, edition);
+        // position of the `mod foo;` declarations, by path of the declared module
+        let mut decls = HashMap::new();
+        for module in files.values() {
+            // the span of a module declared in `cfg_if!` is the span of its declaration
+            let span = match (module.items.first(), module.items.last()) {
+                (Some(first), Some(last)) => first.span.to(last.span),
+                _ => module.span,
+            };
+            let scope = defs.add_scope(module.mod_path.clone(), span, None, None, false);
+            defs.collect_items(&module.items, scope, &sess, edition, &mut decls);
+        }
+        defs.link_scopes(&decls);
+        if !defs.macros.is_empty() {
+            defs.sess = Some(sess);
+        }
+        defs
+    }
+
+    fn add_scope(
+        &mut self,
+        path: Vec<String>,
+        span: Span,
+        parent: Option<usize>,
+        decl: Option<Span>,
+        macro_use: bool,
+    ) -> usize {
+        self.scopes.push(Scope {
+            path,
+            lo: span.lo(),
+            hi: span.hi(),
+            parent,
+            decl,
+            macro_use,
+            defs: vec![],
+        });
+        self.scopes.len() - 1
+    }
+
+    fn collect_items(
+        &mut self,
+        items: &[ptr::P<ast::Item>],
+        scope: usize,
+        sess: &Session,
+        edition: Edition,
+        decls: &mut HashMap<Vec<String>, (Span, bool)>,
+    ) {
+        for item in items {
+            match item.kind {
+                ast::ItemKind::MacroDef(ref def) if def.macro_rules => {
+                    let errors = sess.diagnostic().err_count();
+                    let ext = compile_declarative_macro(sess, &Features::default(), item, edition);
+                    if sess.diagnostic().err_count() > errors {
+                        sess.diagnostic().reset_err_count();
+                        eprintln!("warning: could not parse macro `{}`", item.ident);
+                        continue;
+                    }
+                    self.macros.push(MacroDef {
+                        name: item.ident.name,
+                        ext: Lrc::new(ext),
+                    });
+                    let index = self.macros.len() - 1;
+                    self.scopes[scope].defs.push((item.span.hi(), index));
+                    if item
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.has_name(sym::macro_export))
+                    {
+                        self.exported.insert(item.ident.name, index);
+                    }
+                }
+                ast::ItemKind::Mod(..) if is_mod_decl(item) => {
+                    let mut path = self.scopes[scope].path.clone();
+                    path.push(item.ident.to_string());
+                    let macro_use = item.attrs.iter().any(|attr| attr.has_name(sym::macro_use));
+                    decls.insert(path, (item.span, macro_use));
+                }
+                ast::ItemKind::Mod(_, ast::ModKind::Loaded(ref items, _, inner_span)) => {
+                    let mut path = self.scopes[scope].path.clone();
+                    path.push(item.ident.to_string());
+                    let macro_use = item.attrs.iter().any(|attr| attr.has_name(sym::macro_use));
+                    let inner =
+                        self.add_scope(path, inner_span, Some(scope), Some(item.span), macro_use);
+                    self.collect_items(items, inner, sess, edition, decls);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Link the file modules to the modules declaring them, and make the macros of
+    /// `#[macro_use]` modules visible in their parent.
+    fn link_scopes(&mut self, decls: &HashMap<Vec<String>, (Span, bool)>) {
+        let by_path: HashMap<Vec<String>, usize> = self
+            .scopes
+            .iter()
+            .enumerate()
+            .map(|(index, scope)| (scope.path.clone(), index))
+            .collect();
+        for scope in self.scopes.iter_mut() {
+            if scope.parent.is_some() || scope.path.is_empty() {
+                continue;
+            }
+            let parent_path = &scope.path[..scope.path.len() - 1];
+            scope.parent = by_path.get(parent_path).cloned();
+            // modules declared in `cfg_if!` have no known declaration
+            if let Some(&(span, macro_use)) = decls.get(&scope.path) {
+                scope.decl = Some(span);
+                scope.macro_use = macro_use;
+            }
+        }
+        // innermost modules first, for nested `#[macro_use]` modules
+        let mut order: Vec<usize> = (0..self.scopes.len()).collect();
+        order.sort_by_key(|&index| std::cmp::Reverse(self.scopes[index].path.len()));
+        for index in order {
+            let scope = &self.scopes[index];
+            if let (true, Some(parent), Some(decl)) = (scope.macro_use, scope.parent, scope.decl) {
+                let defs: Vec<_> = scope
+                    .defs
+                    .iter()
+                    .map(|&(_, def)| (decl.hi(), def))
+                    .collect();
+                self.scopes[parent].defs.extend(defs);
+            }
+        }
+    }
+
+    /// Find the macro named by `path`, invoked at `pos`.
+    fn lookup(&self, path: &ast::Path, pos: BytePos) -> Option<&MacroDef> {
+        let index = match path.segments.as_slice() {
+            [segment] => self.lookup_textual(segment.ident.name, pos).or_else(|| {
+                match self.innermost_scope(pos) {
+                    Some(scope) if self.scopes[scope].path.is_empty() => {
+                        self.exported.get(&segment.ident.name).cloned()
+                    }
+                    _ => None,
+                }
+            }),
+            [root, segment]
+                if root.ident.name == kw::Crate || root.ident.name == kw::DollarCrate =>
+            {
+                self.exported.get(&segment.ident.name).cloned()
+            }
+            _ => None,
+        }?;
+        self.macros.get(index)
+    }
+
+    /// Find the most recent definition of the macro visible at `pos`, in the enclosing modules.
+    fn lookup_textual(&self, name: Symbol, mut pos: BytePos) -> Option<usize> {
+        let mut scope = self.innermost_scope(pos);
+        while let Some(index) = scope {
+            let s = &self.scopes[index];
+            let def = s
+                .defs
+                .iter()
+                .filter(|&&(visible, def)| visible <= pos && self.macros[def].name == name)
+                .max_by_key(|&&(visible, _)| visible);
+            if let Some(&(_, def)) = def {
+                return Some(def);
+            }
+            pos = s.decl.map_or(BytePos(u32::MAX), |decl| decl.lo());
+            scope = s.parent;
+        }
+        None
+    }
+
+    fn innermost_scope(&self, pos: BytePos) -> Option<usize> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .filter(|(_, scope)| scope.lo <= pos && pos < scope.hi)
+            .min_by_key(|(_, scope)| scope.hi - scope.lo)
+            .map(|(index, _)| index)
+    }
+
+    /// Expand an item-position macro invocation, at `span`.
+    pub(crate) fn expand(
+        &self,
+        mac: &ast::MacCall,
+        span: Span,
+    ) -> Result<Vec<ptr::P<ast::Item>>, String> {
+        let name = pprust::path_to_string(&mac.path);
+        // invocations produced by an expansion are looked up from the original invocation
+        let (sess, def) = match (
+            &self.sess,
+            self.lookup(&mac.path, span.source_callsite().lo()),
+        ) {
+            (Some(sess), Some(def)) => (sess, def),
+            _ => return Err(format!("macro `{}` is not defined in the crate", name)),
+        };
+        let expander = match def.ext.kind {
+            SyntaxExtensionKind::LegacyBang(ref expander) => expander,
+            _ => return Err(format!("`{}` is not a bang macro", name)),
+        };
+        let mut resolver = Resolver;
+        let mut ecx = ExtCtxt::new(
+            sess,
+            ExpansionConfig::default("crate".to_owned()),
+            &mut resolver,
+            None,
+        );
+        let expn_data = def.ext.expn_data(ExpnId::root(), span, def.name, None);
+        ecx.current_expansion.id = ExpnId::fresh(Some(expn_data));
+        let errors = sess.diagnostic().err_count();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            expander
+                .expand(&mut ecx, span, mac.args.inner_tokens())
+                .make_items()
+        }));
+        let failed = sess.diagnostic().err_count() > errors;
+        sess.diagnostic().reset_err_count();
+        match result {
+            Ok(Some(items)) if !failed => {
+                // `$crate` is this crate
+                hygiene::update_dollar_crate_names(|_| kw::Crate);
+                Ok(items.into_iter().collect())
+            }
+            Ok(_) => Err(format!("expansion of macro `{}` failed", name)),
+            Err(_) => Err(format!("expansion of macro `{}` panicked", name)),
+        }
+    }
+}
+
+/// Create a compiler session sharing the source map of `parse_sess`, without diagnostics.
+fn new_session(parse_sess: &ParseSess, edition: Edition) -> Session {
+    let mut opts = config::Options::default();
+    opts.edition = edition;
+    let mut sess = rustc_session::build_session(
+        opts,
+        None,
+        Registry::new(&[]),
+        DiagnosticOutput::Raw(Box::new(io::sink())),
+        FxHashMap::default(),
+        None,
+        None,
+    );
+    let source_map = parse_sess.inner().source_map().clone();
+    sess.parse_sess = RawParseSess::with_span_handler(
+        Handler::with_emitter(true, None, silent_emitter()),
+        source_map,
+    );
+    sess
+}
+
+/// Name resolution for the expansion of a single invocation.
+///
+/// `macro_rules!` expansion does not resolve any name, nested invocations are expanded by the
+/// caller.
+struct Resolver;
+
+impl ResolverExpand for Resolver {
+    fn next_node_id(&mut self) -> ast::NodeId {
+        ast::DUMMY_NODE_ID
+    }
+
+    fn resolve_dollar_crates(&mut self) {
+        hygiene::update_dollar_crate_names(|_| kw::Crate);
+    }
+
+    fn visit_ast_fragment_with_placeholders(&mut self, _expn_id: ExpnId, _fragment: &AstFragment) {}
+
+    fn register_builtin_macro(&mut self, _ident: Ident, _ext: SyntaxExtensionKind) {}
+
+    fn expansion_for_ast_pass(
+        &mut self,
+        _call_site: Span,
+        _pass: AstPass,
+        _features: &[Symbol],
+        _parent_module_id: Option<ast::NodeId>,
+    ) -> ExpnId {
+        ExpnId::root()
+    }
+
+    fn resolve_imports(&mut self) {}
+
+    fn resolve_macro_invocation(
+        &mut self,
+        _invoc: &Invocation,
+        _eager_expansion_root: ExpnId,
+        _force: bool,
+    ) -> Result<Lrc<SyntaxExtension>, Indeterminate> {
+        Err(Indeterminate)
+    }
+
+    fn check_unused_macros(&mut self) {}
+
+    fn lint_node_id(&self, _expn_id: ExpnId) -> ast::NodeId {
+        ast::CRATE_NODE_ID
+    }
+
+    fn has_derive_copy(&self, _expn_id: ExpnId) -> bool {
+        false
+    }
+
+    fn resolve_derives(
+        &mut self,
+        _expn_id: ExpnId,
+        _force: bool,
+        _derive_paths: &dyn Fn() -> DeriveResolutions,
+    ) -> Result<(), Indeterminate> {
+        Err(Indeterminate)
+    }
+
+    fn take_derive_resolutions(&mut self, _expn_id: ExpnId) -> Option<DeriveResolutions> {
+        None
+    }
+
+    fn cfg_accessible(
+        &mut self,
+        _expn_id: ExpnId,
+        _path: &ast::Path,
+    ) -> Result<bool, Indeterminate> {
+        Err(Indeterminate)
+    }
+}
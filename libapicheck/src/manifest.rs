@@ -0,0 +1,256 @@
+//! Reading of Cargo manifests (`Cargo.toml`).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use json::JsonValue;
+use rustc_span::edition::Edition;
+
+use crate::ErrorKind;
+
+/// Maximum number of features for which all combinations are enumerated.
+const MAX_MATRIX_FEATURES: usize = 10;
+
+/// The subset of a Cargo manifest used by apicheck.
+#[derive(Debug)]
+pub struct Manifest {
+    pub path: PathBuf,
+    /// Package name (`None` for a virtual manifest).
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// Package edition, as declared or inherited from the workspace.
+    pub edition: Option<String>,
+    /// Path of the library root, as declared in the `[lib]` section.
+    pub lib_path: Option<PathBuf>,
+    /// Declared features, and the features they enable.
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Workspace members, as declared (they may be glob patterns, like `crates/*`).
+    pub workspace_members: Vec<String>,
+    /// Paths excluded from the workspace members.
+    pub workspace_exclude: Vec<String>,
+}
+
+impl Manifest {
+    pub fn from_path(path: &Path) -> Result<Manifest, ErrorKind> {
+        let content = fs::read_to_string(path)?;
+        let value: toml::Value = content
+            .parse()
+            .map_err(|e: toml::de::Error| invalid_manifest(path, &e.to_string()))?;
+        let mut features = BTreeMap::new();
+        if let Some(table) = value.get("features").and_then(|f| f.as_table()) {
+            for (name, deps) in table {
+                let deps = deps
+                    .as_array()
+                    .ok_or_else(|| invalid_manifest(path, "features must be arrays"))?
+                    .iter()
+                    .filter_map(|d| d.as_str().map(|s| s.to_owned()))
+                    .collect();
+                features.insert(name.clone(), deps);
+            }
+        }
+        let package = value.get("package");
+        let package_str = |key: &str| {
+            package
+                .and_then(|p| p.get(key))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_owned())
+        };
+        let edition = match package.and_then(|p| p.get("edition")) {
+            Some(toml::Value::String(edition)) => Some(edition.clone()),
+            Some(_) => workspace_edition(path)?,
+            None => None,
+        };
+        let lib_path = value
+            .get("lib")
+            .and_then(|lib| lib.get("path"))
+            .and_then(|p| p.as_str())
+            .map(PathBuf::from);
+        let workspace_paths = |key: &str| -> Vec<String> {
+            value
+                .get("workspace")
+                .and_then(|w| w.get(key))
+                .and_then(|m| m.as_array())
+                .map(|members| {
+                    members
+                        .iter()
+                        .filter_map(|m| m.as_str().map(|s| s.to_owned()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Ok(Manifest {
+            path: path.to_path_buf(),
+            name: package_str("name"),
+            version: package_str("version"),
+            edition,
+            lib_path,
+            features,
+            workspace_members: workspace_paths("members"),
+            workspace_exclude: workspace_paths("exclude"),
+        })
+    }
+
+    /// Directory containing the manifest.
+    pub fn dir(&self) -> &Path {
+        match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        }
+    }
+
+    /// Returns the edition of the package (2015 if not declared, as for cargo).
+    pub fn edition(&self) -> Result<Edition, ErrorKind> {
+        match self.edition {
+            Some(ref edition) => Edition::from_str(edition).map_err(|_| {
+                invalid_manifest(&self.path, &format!("unsupported edition {:?}", edition))
+            }),
+            None => Ok(Edition::Edition2015),
+        }
+    }
+
+    /// Returns the name and the version of the package, as recorded in the API description.
+    pub fn crate_to_json(&self) -> JsonValue {
+        let mut krate = JsonValue::new_object();
+        krate["name"] = self.name.clone().into();
+        krate["version"] = self.version.clone().into();
+        krate
+    }
+
+    /// Returns the root file of the library target.
+    pub fn lib_root(&self) -> Result<PathBuf, ErrorKind> {
+        let root = match self.lib_path {
+            Some(ref path) => self.dir().join(path),
+            None => self.dir().join("src").join("lib.rs"),
+        };
+        if root.is_file() {
+            Ok(root)
+        } else {
+            Err(invalid_manifest(&self.path, "no library target"))
+        }
+    }
+
+    /// Returns the manifests of the workspace members.
+    ///
+    /// As for cargo, members are glob patterns, and the directories matching a pattern are
+    /// skipped if they are in an excluded path or have no manifest.
+    pub fn member_manifests(&self) -> Result<Vec<PathBuf>, ErrorKind> {
+        let excluded: Vec<PathBuf> = self
+            .workspace_exclude
+            .iter()
+            .map(|path| self.dir().join(path))
+            .collect();
+        let mut manifests = Vec::new();
+        for member in &self.workspace_members {
+            let pattern = self.dir().join(member);
+            if !member.contains(|c| c == '*' || c == '?' || c == '[') {
+                // not a pattern, the member must exist
+                manifests.push(pattern.join("Cargo.toml"));
+                continue;
+            }
+            let paths = glob::glob(&pattern.to_string_lossy()).map_err(|e| {
+                invalid_manifest(
+                    &self.path,
+                    &format!("invalid workspace member {:?}: {}", member, e),
+                )
+            })?;
+            // glob returns the paths in alphabetical order
+            for dir in paths.filter_map(|p| p.ok()) {
+                let manifest = dir.join("Cargo.toml");
+                if manifest.is_file() && !excluded.iter().any(|ex| dir.starts_with(ex)) {
+                    manifests.push(manifest);
+                }
+            }
+        }
+        Ok(manifests)
+    }
+
+    /// Returns the set of features enabled when enabling `requested`.
+    ///
+    /// Dependencies (`dep:foo`) and features of dependencies (`foo/bar`) are not included.
+    pub fn resolve_features(&self, requested: &[String]) -> Vec<String> {
+        let mut enabled = BTreeSet::new();
+        let mut stack: Vec<&str> = requested.iter().map(|s| s.as_str()).collect();
+        while let Some(feature) = stack.pop() {
+            if feature.contains('/') || feature.starts_with("dep:") {
+                continue;
+            }
+            if enabled.insert(feature.to_owned()) {
+                if let Some(deps) = self.features.get(feature) {
+                    stack.extend(deps.iter().map(|s| s.as_str()));
+                }
+            }
+        }
+        enabled.into_iter().collect()
+    }
+
+    /// Returns every combination of declared features (excluding `default`).
+    pub fn feature_combinations(&self) -> Result<Vec<Vec<String>>, ErrorKind> {
+        let names: Vec<&String> = self
+            .features
+            .keys()
+            .filter(|name| name.as_str() != "default")
+            .collect();
+        if names.len() > MAX_MATRIX_FEATURES {
+            return Err(invalid_manifest(
+                &self.path,
+                &format!(
+                    "too many features for a feature matrix ({}, maximum {})",
+                    names.len(),
+                    MAX_MATRIX_FEATURES
+                ),
+            ));
+        }
+        let mut combinations = Vec::new();
+        for mask in 0..(1u32 << names.len()) {
+            let set: Vec<String> = names
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, name)| (*name).clone())
+                .collect();
+            combinations.push(set);
+        }
+        Ok(combinations)
+    }
+}
+
+/// Look for `Cargo.toml` in `start` and its parent directories.
+pub fn find_manifest(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|path| path.is_file())
+}
+
+/// Returns the edition declared in `[workspace.package]`, for a member using
+/// `edition.workspace = true`.
+fn workspace_edition(member: &Path) -> Result<Option<String>, ErrorKind> {
+    let start = match member.parent().and_then(|dir| dir.parent()) {
+        Some(dir) => dir,
+        None => return Ok(None),
+    };
+    for dir in start.ancestors() {
+        let path = dir.join("Cargo.toml");
+        if !path.is_file() {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        let value: toml::Value = content
+            .parse()
+            .map_err(|e: toml::de::Error| invalid_manifest(&path, &e.to_string()))?;
+        if let Some(workspace) = value.get("workspace") {
+            return Ok(workspace
+                .get("package")
+                .and_then(|p| p.get("edition"))
+                .and_then(|e| e.as_str())
+                .map(|s| s.to_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn invalid_manifest(path: &Path, msg: &str) -> ErrorKind {
+    ErrorKind::InvalidManifest(path.to_path_buf(), msg.to_owned())
+}
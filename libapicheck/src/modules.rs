@@ -5,13 +5,14 @@ use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use rustc_ast::ast;
-// use rustc_ast::visit::Visitor;
+use rustc_ast::visit::Visitor;
 use rustc_ast::AstLike;
 use rustc_span::symbol::{self, sym, Symbol};
 use rustc_span::Span;
 use thiserror::Error;
 
 use crate::attr::MetaVisitor;
+use crate::cfg::{cfg_of_attrs, CfgExpr, CfgSet};
 use config::FileName;
 use crate::items::is_mod_decl;
 use crate::syntux::parser::{
@@ -32,6 +33,10 @@ pub(crate) struct Module<'a> {
     attrs: Cow<'a, Vec<ast::Attribute>>,
     inner_attr: Vec<ast::Attribute>,
     pub(crate) span: Span,
+    /// The conditions of the `cfg_if!` branches declaring the module, if any.
+    pub(crate) cfg: Option<CfgExpr>,
+    /// The names of the modules leading to this module from the crate root.
+    pub(crate) mod_path: Vec<String>,
 }
 
 impl<'a> Module<'a> {
@@ -52,8 +57,15 @@ impl<'a> Module<'a> {
             inner_attr,
             span: mod_span,
             ast_mod_kind,
+            cfg: None,
+            mod_path: vec![],
         }
     }
+
+    /// Returns the logical path of the module, like `crate::a::b`.
+    pub(crate) fn logical_path(&self) -> String {
+        logical_path(&self.mod_path)
+    }
 }
 
 impl<'a> AstLike for Module<'a> {
@@ -75,6 +87,11 @@ pub(crate) struct ModResolver<'ast, 'sess> {
     directory: Directory,
     file_map: FileModMap<'ast>,
     recursive: bool,
+    cfg: Option<&'sess CfgSet>,
+    /// The conditions of the `cfg_if!` branches enclosing the module being visited.
+    cfg_context: Option<CfgExpr>,
+    /// The names of the modules enclosing the module being visited.
+    mod_path: Vec<String>,
 }
 
 /// Represents errors while trying to resolve modules.
@@ -111,6 +128,7 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
         parse_sess: &'sess ParseSess,
         directory_ownership: DirectoryOwnership,
         recursive: bool,
+        cfg: Option<&'sess CfgSet>,
     ) -> Self {
         ModResolver {
             directory: Directory {
@@ -120,9 +138,37 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
             file_map: BTreeMap::new(),
             parse_sess,
             recursive,
+            cfg,
+            cfg_context: None,
+            mod_path: vec![],
+        }
+    }
+
+    /// Returns `false` if the `cfg` attributes of a module disable it.
+    fn is_cfg_enabled(&self, attrs: &[ast::Attribute]) -> bool {
+        self.is_cfg_expr_enabled(cfg_of_attrs(attrs).as_ref())
+    }
+
+    /// Returns `false` if a condition (of `cfg` attributes or of a `cfg_if!` branch) is not
+    /// satisfied by the enabled cfg options. Without cfg options, everything is enabled.
+    fn is_cfg_expr_enabled(&self, expr: Option<&CfgExpr>) -> bool {
+        match (self.cfg, expr) {
+            (Some(cfg), Some(expr)) => expr.eval(cfg),
+            _ => true,
         }
     }
 
+    /// Returns `true` if the file matches the ignore list.
+    fn is_ignored(&self, path: &Path) -> bool {
+        self.parse_sess
+            .ignore_file(&FileName::Real(path.to_path_buf()))
+    }
+
+    /// Returns `true` if the module being visited matches a module pattern of the ignore list.
+    fn is_module_ignored(&self) -> bool {
+        self.parse_sess.ignore_module(&logical_path(&self.mod_path))
+    }
+
     /// Creates a map that maps a file name to the module in AST.
     pub(crate) fn visit_crate(
         mut self,
@@ -153,20 +199,36 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
 
     /// Visit `cfg_if` macro and look for module declarations.
     fn visit_cfg_if(&mut self, item: Cow<'ast, ast::Item>) -> Result<(), ModuleResolutionError> {
-        let _ = item;
-        unimplemented!();
-        // let mut visitor = visitor::CfgIfVisitor::new(self.parse_sess);
-        // visitor.visit_item(&item);
-        // for module_item in visitor.mods() {
-        //     if let ast::ItemKind::Mod(ref sub_mod) = module_item.item.kind {
-        //         self.visit_sub_mod(Module::new(
-        //             Cow::Owned(sub_mod.clone()),
-        //             Some(Cow::Owned(module_item.item)),
-        //             &[],
-        //         ))?;
-        //     }
-        // }
-        // Ok(())
+        let mut visitor = visitor::CfgIfVisitor::new(self.parse_sess);
+        visitor.visit_item(&item);
+        for module_item in visitor.mods() {
+            if !self.is_cfg_expr_enabled(module_item.cfg.as_ref())
+                || !self.is_cfg_enabled(&module_item.item.attrs)
+            {
+                continue;
+            }
+            if let ast::ItemKind::Mod(_, ref sub_mod_kind) = module_item.item.kind {
+                // inline modules are visited using their items, external ones are parsed
+                let items = match sub_mod_kind {
+                    ast::ModKind::Loaded(items, ast::Inline::Yes, _) => items.clone(),
+                    _ => vec![],
+                };
+                let old_cfg_context = self.cfg_context.clone();
+                self.cfg_context = CfgExpr::and(self.cfg_context.clone(), module_item.cfg.clone());
+                let result = self.visit_sub_mod(
+                    &module_item.item,
+                    Module::new(
+                        module_item.item.span,
+                        Some(Cow::Owned(sub_mod_kind.clone())),
+                        Cow::Owned(items),
+                        Cow::Owned(vec![]),
+                    ),
+                );
+                self.cfg_context = old_cfg_context;
+                result?;
+            }
+        }
+        Ok(())
     }
 
     /// Visit modules defined inside macro calls.
@@ -176,9 +238,12 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
     ) -> Result<(), ModuleResolutionError> {
         for item in items {
             if is_cfg_if(&item) {
-                unimplemented!();
-                // self.visit_cfg_if(Cow::Owned(item.into_inner()))?;
-                // continue;
+                self.visit_cfg_if(Cow::Owned(item.into_inner()))?;
+                continue;
+            }
+
+            if !self.is_cfg_enabled(&item.attrs) {
+                continue;
             }
 
             if let ast::ItemKind::Mod(_, ref sub_mod_kind) = item.kind {
@@ -204,11 +269,15 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
     ) -> Result<(), ModuleResolutionError> {
         for item in items {
             if is_cfg_if(item) {
-                unimplemented!();
-                // let result = self.visit_cfg_if(Cow::Borrowed(item));
-                // if result.is_err() && self.recursive {
-                //     return result;
-                // }
+                let result = self.visit_cfg_if(Cow::Borrowed(item));
+                if result.is_err() && self.recursive {
+                    return result;
+                }
+                continue;
+            }
+
+            if !self.is_cfg_enabled(&item.attrs) {
+                continue;
             }
 
             if let ast::ItemKind::Mod(_, ref sub_mod_kind) = item.kind {
@@ -233,11 +302,17 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
         sub_mod: Module<'ast>,
     ) -> Result<(), ModuleResolutionError> {
         let old_directory = self.directory.clone();
+        self.mod_path.push(item.ident.to_string());
+        if self.is_module_ignored() {
+            self.mod_path.pop();
+            return Ok(());
+        }
         let sub_mod_kind = self.peek_sub_mod(item, &sub_mod)?;
         if let Some(sub_mod_kind) = sub_mod_kind {
             self.insert_sub_mod(sub_mod_kind.clone())?;
             self.visit_sub_mod_inner(sub_mod, sub_mod_kind)?;
         }
+        self.mod_path.pop();
         self.directory = old_directory;
         Ok(())
     }
@@ -267,13 +342,17 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
         sub_mod_kind: SubModKind<'c, 'ast>,
     ) -> Result<(), ModuleResolutionError> {
         match sub_mod_kind {
-            SubModKind::External(mod_path, _, sub_mod) => {
+            SubModKind::External(mod_path, _, mut sub_mod) => {
+                sub_mod.cfg = self.cfg_context.clone();
+                sub_mod.mod_path = self.mod_path.clone();
                 self.file_map
                     .entry(FileName::Real(mod_path))
                     .or_insert(sub_mod);
             }
             SubModKind::MultiExternal(mods) => {
-                for (mod_path, _, sub_mod) in mods {
+                for (mod_path, _, mut sub_mod) in mods {
+                    sub_mod.cfg = self.cfg_context.clone();
+                    sub_mod.mod_path = self.mod_path.clone();
                     self.file_map
                         .entry(FileName::Real(mod_path))
                         .or_insert(sub_mod);
@@ -323,7 +402,7 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
             self.directory = directory;
         }
         match (sub_mod.ast_mod_kind, sub_mod.items) {
-            (Some(Cow::Borrowed(ast::ModKind::Loaded(items, ast::Inline::No, _))), _) => {
+            (Some(Cow::Borrowed(ast::ModKind::Loaded(items, _, _))), _) => {
                 self.visit_mod_from_ast(&items)
             }
             (Some(Cow::Owned(..)), Cow::Owned(items)) => self.visit_mod_outside_ast(items),
@@ -343,7 +422,7 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
             DirectoryOwnership::UnownedViaBlock => None,
         };
         if let Some(path) = Parser::submod_path_from_attr(attrs, &self.directory.path) {
-            if self.parse_sess.is_file_parsed(&path) {
+            if self.parse_sess.is_file_parsed(&path) || self.is_ignored(&path) {
                 return Ok(None);
             }
             return match Parser::parse_file_as_module(self.parse_sess, &path, sub_mod.span) {
@@ -382,6 +461,13 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
                 ..
             }) => {
                 let outside_mods_empty = mods_outside_ast.is_empty();
+                if self.is_ignored(&file_path) {
+                    return Ok(if outside_mods_empty {
+                        None
+                    } else {
+                        Some(SubModKind::MultiExternal(mods_outside_ast))
+                    });
+                }
                 let should_insert = !mods_outside_ast
                     .iter()
                     .any(|(outside_path, _, _)| outside_path == &file_path);
@@ -493,7 +579,7 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
         for path in path_visitor.paths() {
             let mut actual_path = self.directory.path.clone();
             actual_path.push(&path);
-            if !actual_path.exists() {
+            if !actual_path.exists() || self.is_ignored(&actual_path) {
                 continue;
             }
             if self.parse_sess.is_file_parsed(&actual_path) {
@@ -527,6 +613,17 @@ impl<'ast, 'sess, 'c> ModResolver<'ast, 'sess> {
     }
 }
 
+/// Returns the logical path of a module from the names of the modules leading to it, like
+/// `crate::a::b`.
+fn logical_path(mod_path: &[String]) -> String {
+    let mut path = String::from("crate");
+    for name in mod_path {
+        path.push_str("::");
+        path.push_str(name);
+    }
+    path
+}
+
 fn path_value(attr: &ast::Attribute) -> Option<Symbol> {
     if attr.has_name(sym::path) {
         attr.value_str()
@@ -542,7 +639,7 @@ fn find_path_value(attrs: &[ast::Attribute]) -> Option<Symbol> {
     attrs.iter().flat_map(path_value).next()
 }
 
-fn is_cfg_if(item: &ast::Item) -> bool {
+pub(crate) fn is_cfg_if(item: &ast::Item) -> bool {
     match item.kind {
         ast::ItemKind::MacCall(ref mac) => {
             if let Some(first_segment) = mac.path.segments.first() {
@@ -2,10 +2,13 @@ use rustc_ast::ast;
 use rustc_ast::visit::Visitor;
 use rustc_span::Symbol;
 
+use crate::cfg::CfgExpr;
 use crate::{attr::MetaVisitor, syntux::parser::Parser, syntux::session::ParseSess};
 
 pub(crate) struct ModItem {
     pub(crate) item: ast::Item,
+    /// The condition of the `cfg_if!` branch containing the item.
+    pub(crate) cfg: Option<CfgExpr>,
 }
 
 /// Traverse `cfg_if!` macro and fetch modules.
@@ -61,8 +64,16 @@ impl<'a, 'ast: 'a> CfgIfVisitor<'a> {
         };
 
         let items = Parser::parse_cfg_if(self.parse_sess, mac)?;
-        self.mods
-            .append(&mut items.into_iter().map(|item| ModItem { item }).collect());
+        self.mods.append(
+            &mut items
+                .into_iter()
+                .filter(|cfg_if_item| matches!(cfg_if_item.item.kind, ast::ItemKind::Mod(..)))
+                .map(|cfg_if_item| ModItem {
+                    item: cfg_if_item.item,
+                    cfg: cfg_if_item.cfg,
+                })
+                .collect(),
+        );
 
         Ok(())
     }
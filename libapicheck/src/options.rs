@@ -0,0 +1,215 @@
+//! Command-line options shared by `apicheck` and `cargo apicheck`.
+
+use std::path::{Path, PathBuf};
+
+use getopts::{Matches, Options};
+
+use crate::cfg::CfgSet;
+use crate::config::{Config, FileName, OutputFormat};
+use crate::ErrorKind;
+
+/// Register the options setting up the extraction, its configuration and its output.
+pub fn register_options(opts: &mut Options) {
+    opts.optflagmulti("d", "debug", "display debug information");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt("o", "output", "output file name", "FILE");
+    opts.optopt("", "manifest-path", "path to Cargo.toml", "PATH");
+    opts.optopt(
+        "",
+        "edition",
+        "edition of the crate (2015, 2018 or 2021, default: edition of the manifest or 2018)",
+        "EDITION",
+    );
+    opts.optopt(
+        "",
+        "config-path",
+        "path to the configuration file (default: apicheck.toml in the input directory or its parents)",
+        "PATH",
+    );
+    opts.optmulti(
+        "",
+        "ignore",
+        "ignore the files matching a gitignore-style pattern (can be repeated)",
+        "PATTERN",
+    );
+    opts.optmulti(
+        "",
+        "include",
+        "keep only the items whose path matches a pattern, like crate::ffi::** (can be repeated)",
+        "PATTERN",
+    );
+    opts.optmulti(
+        "",
+        "exclude",
+        "remove the items whose path matches a pattern (can be repeated)",
+        "PATTERN",
+    );
+    opts.optmulti(
+        "",
+        "include-kind",
+        "keep only the items of a kind, like function, trait or impl (can be repeated)",
+        "KIND",
+    );
+    opts.optmulti(
+        "",
+        "exclude-kind",
+        "remove the items of a kind (can be repeated)",
+        "KIND",
+    );
+    opts.optmulti(
+        "",
+        "cfg",
+        "enable a cfg option and ignore items disabled by cfg attributes",
+        "SPEC",
+    );
+    opts.optmulti(
+        "",
+        "feature-set",
+        "extract the API for this comma-separated feature set (can be repeated)",
+        "FEATURES",
+    );
+    opts.optflag(
+        "",
+        "feature-matrix",
+        "extract the API for every combination of the declared features",
+    );
+    opts.optflag(
+        "",
+        "expand-macros",
+        "expand invocations of the macro_rules! macros defined in the crate",
+    );
+    opts.optflag(
+        "",
+        "canonical",
+        "sort the output, so that it does not depend on the order of the code",
+    );
+    opts.optflag(
+        "",
+        "ffi",
+        "keep only the FFI surface (exported functions and statics, repr(C) types)",
+    );
+    opts.optflag(
+        "",
+        "ffi-header",
+        "print the FFI surface as a C header-like listing",
+    );
+    opts.optflag(
+        "",
+        "unsafe-surface",
+        "keep only the unsafe surface (unsafe items, static mut, raw pointers)",
+    );
+    opts.optflag(
+        "",
+        "public-dependencies",
+        "list the dependencies exposed in the API",
+    );
+    opts.optflag(
+        "",
+        "resolve-paths",
+        "add the canonical form of types, with fully qualified paths",
+    );
+    opts.optflag(
+        "",
+        "auto-traits",
+        "infer the auto traits (Send, Sync, Unpin) of the types",
+    );
+}
+
+/// Set up the configuration from the options registered by `register_options`.
+///
+/// The configuration file (given with `--config-path`, or looked up from `input_dir`) is read
+/// first, so that the options override its settings.
+pub fn config_from_matches(matches: &Matches, input_dir: &Path) -> Result<Config, ErrorKind> {
+    let mut config = Config::default();
+    config.debug = matches.opt_count("d");
+    config.output = match matches.opt_str("o") {
+        Some(s) => {
+            if &s == "-" {
+                FileName::Stdout
+            } else {
+                FileName::Real(PathBuf::from(s))
+            }
+        }
+        None => FileName::Stdout,
+    };
+    let config_path = match matches.opt_str("config-path") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Config::find_config_file(input_dir),
+    };
+    if let Some(ref path) = config_path {
+        if config.debug > 0 {
+            println!("Using configuration file {}", path.display());
+        }
+        config.load_file(path)?;
+    }
+    if let Some(edition) = matches.opt_str("edition") {
+        config.set_edition(&edition)?;
+    }
+    if matches.opt_present("expand-macros") {
+        config.expand_macros = true;
+    }
+    if matches.opt_present("canonical") {
+        config.canonical = true;
+    }
+    if matches.opt_present("ffi") {
+        config.ffi = true;
+    }
+    if matches.opt_present("ffi-header") {
+        config.ffi = true;
+        config.output_format = OutputFormat::CHeader;
+    }
+    if matches.opt_present("unsafe-surface") {
+        config.unsafe_surface = true;
+    }
+    if matches.opt_present("public-dependencies") {
+        config.public_dependencies = true;
+    }
+    if matches.opt_present("resolve-paths") {
+        config.resolve_paths = true;
+    }
+    if matches.opt_present("auto-traits") {
+        config.auto_traits = true;
+    }
+    for pattern in matches.opt_strs("ignore") {
+        config.add_ignore_pattern(&pattern)?;
+    }
+    for pattern in matches.opt_strs("include") {
+        config.filter.include(&pattern);
+    }
+    for pattern in matches.opt_strs("exclude") {
+        config.filter.exclude(&pattern);
+    }
+    for kind in matches.opt_strs("include-kind") {
+        config.filter.include_kind(&kind);
+    }
+    for kind in matches.opt_strs("exclude-kind") {
+        config.filter.exclude_kind(&kind);
+    }
+    // the feature sets are resolved against the manifest with `Config::resolve_feature_sets`
+    let feature_sets = matches.opt_strs("feature-set");
+    if !feature_sets.is_empty() {
+        config.feature_sets = Some(
+            feature_sets
+                .iter()
+                .map(|s| {
+                    s.split(',')
+                        .map(|f| f.trim().to_owned())
+                        .filter(|f| !f.is_empty())
+                        .collect()
+                })
+                .collect(),
+        );
+    }
+    if matches.opt_present("feature-matrix") {
+        config.feature_matrix = true;
+    }
+    let cfg_options = matches.opt_strs("cfg");
+    if !cfg_options.is_empty() {
+        let mut cfg = config.cfg.take().unwrap_or_else(CfgSet::new);
+        for option in &cfg_options {
+            cfg.insert_str(option);
+        }
+        config.cfg = Some(cfg);
+    }
+    Ok(config)
+}
@@ -3,6 +3,7 @@ use items;
 use json::JsonValue;
 use modules::FileModMap;
 use ApiCheckError;
+use ParseSess;
 
 pub(crate) fn create_json_from_crate<'a>(
     files: &FileModMap,
@@ -11,19 +12,41 @@ pub(crate) fn create_json_from_crate<'a>(
     let mut mod_v: Vec<JsonValue> = Vec::new();
 
     for (filename, module) in files.iter() {
+        if let Some(parse_sess) = context.parse_sess() {
+            if parse_sess.ignore_file(filename) {
+                continue;
+            }
+        }
         if context.config.debug > 0 {
             println!("Processing modules in file {}", filename);
         }
-        let v: Vec<_> = module
-            .items
-            .iter()
-            .filter_map(|ref item| items::check_item(&item, &context))
-            .collect();
+        let mut v = items::check_items(&module.items, &context);
+        if let Some(parse_sess) = context.parse_sess() {
+            remove_ignored_modules(&mut v, &module.logical_path(), parse_sess);
+        }
+        if let Some(ref cfg) = module.cfg {
+            // the module was declared in a `cfg_if!` branch
+            for js in v.iter_mut() {
+                let s = match js["cfg"].as_str() {
+                    Some(item_cfg) => format!("all({}, {})", cfg, item_cfg),
+                    None => cfg.to_string(),
+                };
+                js["cfg"] = JsonValue::String(s);
+            }
+        }
         // println!("v: {:?}", v);
         //
         let mut mod_json = JsonValue::new_object();
         mod_json["path"] = JsonValue::String(format!("{}", filename));
+        mod_json["module"] = JsonValue::String(module.logical_path());
         mod_json["items"] = JsonValue::Array(v);
+        let imports = items::local_imports_to_json(&module.items, context.config.edition);
+        if !imports.is_empty() {
+            mod_json["imports"] = imports;
+        }
+        if context.config.needs_scopes() {
+            mod_json["scope"] = items::scope_to_json(&module.items);
+        }
         if context.config.debug > 0 {
             println!("path={:?}, json=\n{}", filename, mod_json);
         }
@@ -35,3 +58,19 @@ pub(crate) fn create_json_from_crate<'a>(
     // XXX add input name
     Ok(json)
 }
+
+/// Remove the inline modules matching a module pattern of the ignore list. The modules
+/// defined in other files are not even parsed.
+fn remove_ignored_modules(items: &mut Vec<JsonValue>, path: &str, parse_sess: &ParseSess) {
+    items.retain(|js| js["type"] != "mod" || !parse_sess.ignore_module(&mod_path(path, js)));
+    for js in items.iter_mut().filter(|js| js["type"] == "mod") {
+        let path = mod_path(path, js);
+        if let JsonValue::Array(ref mut mod_items) = js["items"] {
+            remove_ignored_modules(mod_items, &path, parse_sess);
+        }
+    }
+}
+
+fn mod_path(parent: &str, js: &JsonValue) -> String {
+    format!("{}::{}", parent, js["name"].as_str().unwrap_or(""))
+}
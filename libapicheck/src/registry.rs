@@ -0,0 +1,86 @@
+//! Access to the sources of published crates in the local Cargo registry.
+//!
+//! No network access is done: the crate must be present in `$CARGO_HOME/registry`,
+//! either unpacked (in `registry/src`) or as a `.crate` archive (in `registry/cache`).
+
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use tempfile::TempDir;
+
+use crate::ErrorKind;
+
+/// The source directory of a crate, possibly unpacked into a temporary directory.
+pub struct CrateSource {
+    /// Directory containing the `Cargo.toml` of the crate
+    pub dir: PathBuf,
+    /// Temporary directory, removed when the source is dropped
+    _tmp: Option<TempDir>,
+}
+
+/// Returns the Cargo home directory (`$CARGO_HOME`, or `~/.cargo`).
+pub fn cargo_home() -> Option<PathBuf> {
+    match env::var_os("CARGO_HOME") {
+        Some(home) => Some(PathBuf::from(home)),
+        None => env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")),
+    }
+}
+
+/// Find the sources of version `version` of crate `name` in the local registry.
+pub fn find_crate_source(name: &str, version: &str) -> Result<CrateSource, ErrorKind> {
+    let not_found = || ErrorKind::CrateNotFound(format!("{}@{}", name, version));
+    let registry = cargo_home().ok_or_else(not_found)?.join("registry");
+    let dir_name = format!("{}-{}", name, version);
+    for index in index_dirs(&registry.join("src")) {
+        let dir = index.join(&dir_name);
+        if dir.join("Cargo.toml").is_file() {
+            return Ok(CrateSource { dir, _tmp: None });
+        }
+    }
+    for index in index_dirs(&registry.join("cache")) {
+        let archive = index.join(format!("{}.crate", dir_name));
+        if archive.is_file() {
+            return unpack_crate(&archive);
+        }
+    }
+    Err(not_found())
+}
+
+/// Unpack a `.crate` (or `.tar.gz`) archive into a temporary directory.
+///
+/// Packaged crates contain a single top-level directory, which is returned as the crate
+/// directory if it has a manifest.
+pub fn unpack_crate(archive: &Path) -> Result<CrateSource, ErrorKind> {
+    let tmp = tempfile::Builder::new().prefix("apicheck-").tempdir()?;
+    let file = File::open(archive)?;
+    tar::Archive::new(GzDecoder::new(file)).unpack(tmp.path())?;
+    let mut dirs: Vec<PathBuf> = fs::read_dir(tmp.path())?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.join("Cargo.toml").is_file())
+        .collect();
+    let dir = match (dirs.pop(), dirs.is_empty()) {
+        (Some(dir), true) => dir,
+        _ => tmp.path().to_path_buf(),
+    };
+    Ok(CrateSource {
+        dir,
+        _tmp: Some(tmp),
+    })
+}
+
+/// Returns the subdirectories of `dir` (one per registry index), sorted.
+fn index_dirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    dirs.sort();
+    dirs
+}
@@ -11,6 +11,7 @@ use rustc_parse::{
 use rustc_span::{sym, symbol::kw, Span};
 
 use crate::attr::first_attr_value_str_by_name;
+use crate::cfg::CfgExpr;
 use crate::syntux::session::ParseSess;
 use crate::Input;
 
@@ -24,6 +25,13 @@ pub(crate) struct Directory {
     pub(crate) ownership: DirectoryOwnership,
 }
 
+/// An item parsed from a branch of a `cfg_if!` invocation.
+pub(crate) struct CfgIfItem {
+    pub(crate) item: ast::Item,
+    /// The condition for the branch to be selected (`None` for an unconditional `else` branch).
+    pub(crate) cfg: Option<CfgExpr>,
+}
+
 /// A parser for Rust source code.
 pub(crate) struct Parser<'a> {
     parser: RawParser<'a>,
@@ -74,12 +82,18 @@ impl<'a> ParserBuilder<'a> {
                 new_parser_from_file(sess, file, None)
             }))
             .map_err(|_| None),
-            Input::Text(text) => rustc_parse::maybe_new_parser_from_source_str(
-                sess,
-                rustc_span::FileName::Custom("stdin".to_owned()),
-                text,
-            )
-            .map_err(|db| Some(db)),
+            Input::Text(text, file) => {
+                let file_name = match file {
+                    Some(file) => {
+                        rustc_span::FileName::Real(rustc_span::RealFileName::LocalPath(file))
+                    }
+                    None => rustc_span::FileName::Custom("stdin".to_owned()),
+                };
+                rustc_parse::maybe_new_parser_from_source_str(sess, file_name, text)
+                    .map_err(|db| Some(db))
+            }
+            // crates are resolved to their root file before parsing
+            Input::Directory(..) | Input::Archive(..) => Err(None),
         }
     }
 }
@@ -183,7 +197,7 @@ impl<'a> Parser<'a> {
     pub(crate) fn parse_cfg_if(
         sess: &'a ParseSess,
         mac: &'a ast::MacCall,
-    ) -> Result<Vec<ast::Item>, &'static str> {
+    ) -> Result<Vec<CfgIfItem>, &'static str> {
         match catch_unwind(AssertUnwindSafe(|| Parser::parse_cfg_if_inner(sess, mac))) {
             Ok(Ok(items)) => Ok(items),
             Ok(err @ Err(_)) => err,
@@ -194,15 +208,18 @@ impl<'a> Parser<'a> {
     fn parse_cfg_if_inner(
         sess: &'a ParseSess,
         mac: &'a ast::MacCall,
-    ) -> Result<Vec<ast::Item>, &'static str> {
+    ) -> Result<Vec<CfgIfItem>, &'static str> {
         let token_stream = mac.args.inner_tokens();
         let mut parser =
             rustc_parse::stream_to_parser(sess.inner(), token_stream.clone(), Some(""));
 
         let mut items = vec![];
         let mut process_if_cfg = true;
+        // conditions of the previous branches, which must be false for the current one to apply
+        let mut previous_cfgs: Vec<CfgExpr> = vec![];
 
         while parser.token.kind != TokenKind::Eof {
+            let mut branch_cfg = None;
             if process_if_cfg {
                 if !parser.eat_keyword(kw::If) {
                     return Err("Expected `if`");
@@ -215,10 +232,23 @@ impl<'a> Parser<'a> {
                 // As such, we just need to advance the parser past the attribute and up to
                 // to the opening brace.
                 // See also https://github.com/rust-lang/rust/pull/79433
-                parser
+                let attr = parser
                     .parse_attribute(rustc_parse::parser::attr::InnerAttrPolicy::Permitted)
                     .map_err(|_| "Failed to parse attributes")?;
+                if !attr.has_name(sym::cfg) {
+                    return Err("Expected a `cfg` attribute in cfg_if branch");
+                }
+                // a branch with an unknown condition cannot be treated as unconditional
+                let cfg = match attr.meta_item_list() {
+                    Some(ref list) if list.len() == 1 => {
+                        list[0].meta_item().and_then(CfgExpr::from_meta_item)
+                    }
+                    _ => None,
+                };
+                branch_cfg = Some(cfg.ok_or("Failed to parse the condition of cfg_if branch")?);
             }
+            let cfg = cfg_if_branch_cfg(branch_cfg.clone(), &previous_cfgs);
+            previous_cfgs.extend(branch_cfg);
 
             if !parser.eat(&TokenKind::OpenDelim(DelimToken::Brace)) {
                 return Err("Expected an opening brace");
@@ -238,9 +268,10 @@ impl<'a> Parser<'a> {
                         );
                     }
                 };
-                if let ast::ItemKind::Mod(..) = item.kind {
-                    items.push(item);
-                }
+                items.push(CfgIfItem {
+                    item,
+                    cfg: cfg.clone(),
+                });
             }
 
             if !parser.eat(&TokenKind::CloseDelim(DelimToken::Brace)) {
@@ -261,3 +292,20 @@ impl<'a> Parser<'a> {
         Ok(items)
     }
 }
+
+/// Returns the condition for a `cfg_if!` branch to be selected: its own condition (`None` for the
+/// `else` branch), and none of the previous conditions.
+fn cfg_if_branch_cfg(cfg: Option<CfgExpr>, previous: &[CfgExpr]) -> Option<CfgExpr> {
+    let mut exprs = Vec::new();
+    if let Some(cfg) = cfg {
+        exprs.push(cfg);
+    }
+    for prev in previous {
+        exprs.push(CfgExpr::Not(Box::new(prev.clone())));
+    }
+    match exprs.len() {
+        0 => None,
+        1 => exprs.pop(),
+        _ => Some(CfgExpr::All(exprs)),
+    }
+}
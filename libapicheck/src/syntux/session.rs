@@ -21,6 +21,17 @@ use crate::ignore_path::IgnorePathSet;
 use crate::{Config, ErrorKind, FileName};
 
 
+/// The position of an item in the source code.
+pub(crate) struct Location {
+    pub(crate) file: String,
+    /// First line (starting at 1)
+    pub(crate) line: usize,
+    /// First column (starting at 1)
+    pub(crate) column: usize,
+    /// Last line (starting at 1)
+    pub(crate) end_line: usize,
+}
+
 /// ParseSess holds structs necessary for constructing a parser.
 pub(crate) struct ParseSess {
     parse_sess: RawParseSess,
@@ -38,7 +49,7 @@ impl Emitter for SilentEmitter {
     fn emit_diagnostic(&mut self, _db: &Diagnostic) {}
 }
 
-fn silent_emitter() -> Box<dyn Emitter + Send> {
+pub(crate) fn silent_emitter() -> Box<dyn Emitter + Send> {
     Box::new(SilentEmitter {})
 }
 
@@ -171,6 +182,11 @@ impl ParseSess {
         self.ignore_path_set.as_ref().is_match(&path)
     }
 
+    /// Returns `true` if the module with the logical path `path` is in the ignore list.
+    pub(crate) fn ignore_module(&self, path: &str) -> bool {
+        self.ignore_path_set.as_ref().is_module_match(path)
+    }
+
     pub(crate) fn set_silent_emitter(&mut self) {
         self.parse_sess.span_diagnostic = Handler::with_emitter(true, None, silent_emitter());
     }
@@ -199,6 +215,25 @@ impl ParseSess {
         self.parse_sess.source_map().span_to_diagnostic_string(span)
     }
 
+    pub(crate) fn span_to_location(&self, span: Span) -> Location {
+        let source_map = self.parse_sess.source_map();
+        let lo = source_map.lookup_char_pos(span.lo());
+        let hi = source_map.lookup_char_pos(span.hi());
+        let file = match lo.file.name {
+            rustc_span::FileName::Real(rustc_span::RealFileName::LocalPath(ref p)) => {
+                p.display().to_string()
+            }
+            rustc_span::FileName::Custom(ref f) if f == "stdin" => format!("{}", FileName::Stdin),
+            ref name => format!("{:?}", name),
+        };
+        Location {
+            file,
+            line: lo.line,
+            column: lo.col.0 + 1,
+            end_line: hi.line,
+        }
+    }
+
     pub(crate) fn inner(&self) -> &RawParseSess {
         &self.parse_sess
     }
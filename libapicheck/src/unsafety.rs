@@ -0,0 +1,125 @@
+//! Unsafe surface: the part of the API which exposes `unsafe` to the users of the crate.
+//!
+//! The unsafe surface contains the `unsafe fn`, `unsafe trait` and `unsafe impl` items, the
+//! `static mut` items, and the public fields and signatures with a raw pointer type. Other
+//! fields of structs and other methods of traits and impls are removed. The counts of each
+//! kind are stored in the `unsafe_summary` key.
+
+use apicheck_common::unsafety::{unsafe_parts, UnsafePart};
+use json::JsonValue;
+
+#[derive(Default)]
+struct Summary {
+    functions: usize,
+    traits: usize,
+    impls: usize,
+    statics: usize,
+    raw_pointers: usize,
+}
+
+impl Summary {
+    fn count(&mut self, parts: &[UnsafePart]) {
+        for part in parts {
+            match *part {
+                UnsafePart::Function => self.functions += 1,
+                UnsafePart::Trait => self.traits += 1,
+                UnsafePart::Impl => self.impls += 1,
+                UnsafePart::StaticMut => self.statics += 1,
+                _ => self.raw_pointers += 1,
+            }
+        }
+    }
+}
+
+/// Keep only the unsafe surface of an API description.
+pub fn unsafe_surface(json: &mut JsonValue) {
+    if json.has_key("crates") {
+        for (_, krate) in json["crates"].entries_mut() {
+            unsafe_surface(krate);
+        }
+        return;
+    }
+    let mut summary = Summary::default();
+    let modules: Vec<JsonValue> = json["modules"]
+        .members()
+        .filter_map(|module| filter_items(module.clone(), &mut summary))
+        .collect();
+    json["modules"] = JsonValue::Array(modules);
+    let mut summary_js = JsonValue::new_object();
+    summary_js["functions"] = JsonValue::from(summary.functions);
+    summary_js["traits"] = JsonValue::from(summary.traits);
+    summary_js["impls"] = JsonValue::from(summary.impls);
+    summary_js["statics"] = JsonValue::from(summary.statics);
+    summary_js["raw_pointers"] = JsonValue::from(summary.raw_pointers);
+    json["unsafe_summary"] = summary_js;
+}
+
+/// Keep the unsafe items of a module, trait or impl, and return it if it still has items.
+fn filter_items(mut json: JsonValue, summary: &mut Summary) -> Option<JsonValue> {
+    let items: Vec<JsonValue> = json["items"]
+        .members()
+        .filter_map(|item| filter_item(item.clone(), summary))
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+    json["items"] = JsonValue::Array(items);
+    Some(json)
+}
+
+fn filter_item(mut item: JsonValue, summary: &mut Summary) -> Option<JsonValue> {
+    if item["type"] == "mod" {
+        return filter_items(item, summary);
+    }
+    let parts = unsafe_parts(&item);
+    match item["type"].as_str() {
+        Some("trait") | Some("impl") => {
+            if parts.is_empty() {
+                return filter_items(item, summary);
+            }
+            // implementing an unsafe trait is unsafe, all its items are kept
+            summary.count(&parts);
+            for method in item["items"].members() {
+                summary.count(&unsafe_parts(method));
+            }
+            Some(item)
+        }
+        Some("function") | Some("method") | Some("static") | Some("const") => {
+            if parts.is_empty() {
+                return None;
+            }
+            summary.count(&parts);
+            Some(item)
+        }
+        Some("struct") | Some("union") | Some("enum") => {
+            if parts.is_empty() {
+                return None;
+            }
+            summary.count(&parts);
+            let is_enum = item["type"] == "enum";
+            let fields: Vec<JsonValue> = item["fields"]
+                .members()
+                .enumerate()
+                .filter(|&(index, field)| {
+                    parts.iter().any(|part| match *part {
+                        // the variants with raw pointer fields are kept
+                        UnsafePart::Field {
+                            variant: Some(ref variant),
+                            ..
+                        } => is_enum && field["name"] == variant.as_str(),
+                        UnsafePart::Field {
+                            variant: None,
+                            index: field_index,
+                            ..
+                        } => !is_enum && field_index == index,
+                        _ => false,
+                    })
+                })
+                .map(|(_, field)| field.clone())
+                .collect();
+            item["fields"] = JsonValue::Array(fields);
+            Some(item)
+        }
+        _ => None,
+    }
+}
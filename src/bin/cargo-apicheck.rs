@@ -0,0 +1,303 @@
+//! `cargo apicheck`: extract the API of the library of a cargo package.
+//!
+//! The library root and the edition (unless set explicitly) are read from `Cargo.toml`.
+//! With `--workspace`, the libraries of all workspace members are extracted into a
+//! description keyed by crate name.
+//!
+//! With `--git-diff OLD NEW`, the API is extracted at two revisions of the git repository
+//! containing the package, and the descriptions are compared with `apidiff`. With
+//! `--baseline NAME@VERSION`, the package is compared with a version of a crate from the
+//! local Cargo registry.
+
+extern crate getopts;
+extern crate json;
+extern crate tempfile;
+
+extern crate libapicheck;
+
+use getopts::Options;
+use json::JsonValue;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use libapicheck::config::{Config, FileName};
+use libapicheck::manifest::{find_manifest, Manifest};
+use libapicheck::{git, options, registry, ErrorKind, Input, OutputFormat};
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {0} apicheck [options]\n       \
+         {0} apicheck [options] --git-diff OLD NEW\n       \
+         {0} apicheck [options] --baseline NAME@VERSION",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() -> Result<(), ErrorKind> {
+    let mut args: Vec<String> = env::args().collect();
+    // when called as `cargo apicheck`, cargo passes the subcommand name as first argument
+    if args.len() > 1 && args[1] == "apicheck" {
+        args.remove(1);
+    }
+    // process options
+    let mut opts = Options::new();
+    options::register_options(&mut opts);
+    opts.optopt("p", "package", "package to check, in a workspace", "NAME");
+    opts.optflag(
+        "",
+        "workspace",
+        "check the libraries of all workspace members",
+    );
+    opts.optopt(
+        "",
+        "git-rev",
+        "extract the API at a revision of the git repository",
+        "REV",
+    );
+    opts.optflag(
+        "",
+        "git-diff",
+        "compare the API at two revisions of the git repository",
+    );
+    opts.optopt(
+        "",
+        "baseline",
+        "compare with a version of a crate from the local Cargo registry",
+        "NAME@VERSION",
+    );
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => panic!("{}", f),
+    };
+    if matches.opt_present("h") {
+        print_usage("cargo", opts);
+        return Ok(());
+    }
+    // find the package
+    let manifest_path = match matches.opt_str("manifest-path") {
+        Some(path) => PathBuf::from(path),
+        None => match find_manifest(&env::current_dir()?) {
+            Some(path) => path,
+            None => {
+                eprintln!("could not find Cargo.toml in the current directory or its parents");
+                ::std::process::exit(1);
+            }
+        },
+    };
+    // setup config
+    let package_dir = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    let config = options::config_from_matches(&matches, package_dir)?;
+    let workspace = matches.opt_present("workspace");
+    let package = matches.opt_str("p");
+    // work !
+    if matches.opt_present("git-diff") {
+        if matches.free.len() != 2 {
+            eprintln!("--git-diff requires two revisions");
+            ::std::process::exit(1);
+        }
+        let old = extract_at_revision(
+            &manifest_path,
+            &matches.free[0],
+            workspace,
+            &package,
+            &config,
+        )?;
+        let new = extract_at_revision(
+            &manifest_path,
+            &matches.free[1],
+            workspace,
+            &package,
+            &config,
+        )?;
+        let rc = diff(&old, &new)?;
+        ::std::process::exit(rc);
+    }
+    if let Some(baseline) = matches.opt_str("baseline") {
+        let manifest = select_package(Manifest::from_path(&manifest_path)?, package)?;
+        let (name, version) = match baseline.find('@') {
+            Some(idx) => (baseline[..idx].to_owned(), baseline[idx + 1..].to_owned()),
+            None => (manifest.name.clone().unwrap_or_default(), baseline),
+        };
+        if config.debug > 0 {
+            println!("Using baseline {}@{}", name, version);
+        }
+        let source = registry::find_crate_source(&name, &version)?;
+        let manifest_name = Path::new("Cargo.toml");
+        let old = extract_from_dir(&source.dir, manifest_name, false, &None, &config)?;
+        let new = extract_from_dir(manifest.dir(), manifest_name, false, &None, &config)?;
+        let rc = diff(&old, &new)?;
+        // exit does not run destructors
+        drop(source);
+        ::std::process::exit(rc);
+    }
+    let json = match matches.opt_str("git-rev") {
+        Some(rev) => extract_at_revision(&manifest_path, &rev, workspace, &package, &config)?,
+        None => extract(&manifest_path, workspace, &package, &config)?,
+    };
+    libapicheck::write_json(&json, &config.output, config.output_format)?;
+    Ok(())
+}
+
+/// Extract the API of the package (or of the whole workspace) described by `manifest_path`.
+fn extract(
+    manifest_path: &Path,
+    workspace: bool,
+    package: &Option<String>,
+    config: &Config,
+) -> Result<JsonValue, ErrorKind> {
+    let manifest = Manifest::from_path(manifest_path)?;
+    if workspace {
+        return extract_workspace(&manifest, config);
+    }
+    let manifest = select_package(manifest, package.clone())?;
+    let mut json = extract_package(&manifest, config)?;
+    json["crate"] = manifest.crate_to_json();
+    Ok(json)
+}
+
+/// Extract the API at a revision of the git repository containing `manifest_path`.
+///
+/// The files of the revision are exported to a temporary directory.
+fn extract_at_revision(
+    manifest_path: &Path,
+    rev: &str,
+    workspace: bool,
+    package: &Option<String>,
+    config: &Config,
+) -> Result<JsonValue, ErrorKind> {
+    let manifest_path = fs::canonicalize(manifest_path)?;
+    let repo = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    let top = fs::canonicalize(git::toplevel(repo)?)?;
+    let relative_path = match manifest_path.strip_prefix(&top) {
+        Ok(path) => path.to_path_buf(),
+        Err(_) => {
+            return Err(ErrorKind::GitError(format!(
+                "{} is not in the git repository {}",
+                manifest_path.display(),
+                top.display()
+            )))
+        }
+    };
+    if config.debug > 0 {
+        println!("Exporting revision {}", rev);
+    }
+    let checkout = git::export_revision(&top, rev)?;
+    extract_from_dir(checkout.path(), &relative_path, workspace, package, config)
+}
+
+/// Extract the API from `dir`, so that file paths are relative to `dir`.
+///
+/// `manifest_path` is relative to `dir`.
+fn extract_from_dir(
+    dir: &Path,
+    manifest_path: &Path,
+    workspace: bool,
+    package: &Option<String>,
+    config: &Config,
+) -> Result<JsonValue, ErrorKind> {
+    let mut json = extract(&dir.join(manifest_path), workspace, package, config)?;
+    libapicheck::relative_paths(&mut json, dir);
+    Ok(json)
+}
+
+/// Compare two API descriptions with `apidiff`, and return its exit code.
+fn diff(old: &JsonValue, new: &JsonValue) -> Result<i32, ErrorKind> {
+    let dir = tempfile::Builder::new().prefix("apicheck-").tempdir()?;
+    let old_path = dir.path().join("old.json");
+    let new_path = dir.path().join("new.json");
+    libapicheck::write_json(
+        old,
+        &FileName::Real(old_path.clone()),
+        OutputFormat::Compact,
+    )?;
+    libapicheck::write_json(
+        new,
+        &FileName::Real(new_path.clone()),
+        OutputFormat::Compact,
+    )?;
+    run_apidiff(&old_path, &new_path)
+}
+
+/// Run `apidiff` (installed next to this program, or in the `PATH`) and return its exit code.
+fn run_apidiff(old: &Path, new: &Path) -> Result<i32, ErrorKind> {
+    let program = env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name("apidiff"))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from("apidiff"));
+    let status = Command::new(program).arg(old).arg(new).status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Extract the API of the library of a package, using the edition of the package unless one
+/// was set explicitly.
+fn extract_package(manifest: &Manifest, config: &Config) -> Result<JsonValue, ErrorKind> {
+    let mut config = config.clone();
+    config.set_default_edition(manifest.edition()?);
+    config.resolve_feature_sets(Some(manifest))?;
+    let lib_root = manifest.lib_root()?;
+    if config.debug > 0 {
+        println!("Processing file {}", lib_root.display());
+    }
+    libapicheck::extract_api(Input::File(lib_root), &config)
+}
+
+/// Extract the API of every library of the workspace, keyed by crate name.
+///
+/// Members without a library target are skipped.
+fn extract_workspace(manifest: &Manifest, config: &Config) -> Result<JsonValue, ErrorKind> {
+    let mut packages = Vec::new();
+    if manifest.name.is_some() {
+        packages.push(manifest.path.clone());
+    }
+    packages.extend(manifest.member_manifests()?);
+    let mut crates = JsonValue::new_object();
+    for path in packages {
+        let member = Manifest::from_path(&path)?;
+        let name = match member.name {
+            Some(ref name) => name.clone(),
+            None => continue,
+        };
+        if member.lib_root().is_err() {
+            if config.debug > 0 {
+                println!("Skipping {}: no library target", name);
+            }
+            continue;
+        }
+        let mut json = extract_package(&member, config)?;
+        json["version"] = member.version.clone().into();
+        crates[name.as_str()] = json;
+    }
+    let mut json = JsonValue::new_object();
+    json["crates"] = crates;
+    Ok(json)
+}
+
+/// Returns the manifest of the package to check.
+///
+/// In a workspace, the package is selected by name among the workspace members.
+fn select_package(manifest: Manifest, package: Option<String>) -> Result<Manifest, ErrorKind> {
+    match package {
+        None if manifest.name.is_some() => return Ok(manifest),
+        Some(ref name) if manifest.name.as_ref() == Some(name) => return Ok(manifest),
+        _ => (),
+    }
+    let mut names = Vec::new();
+    for path in manifest.member_manifests()? {
+        let member = Manifest::from_path(&path)?;
+        if member.name.is_some() && member.name == package {
+            return Ok(member);
+        }
+        names.extend(member.name);
+    }
+    match package {
+        Some(name) => Err(ErrorKind::PackageNotFound(name)),
+        None => Err(ErrorKind::VirtualManifest(
+            manifest.path.clone(),
+            names.join(", "),
+        )),
+    }
+}
@@ -4,13 +4,17 @@ extern crate libapicheck;
 
 use getopts::Options;
 use std::env;
-use std::path::PathBuf;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
-use libapicheck::config::FileName;
-use libapicheck::{ErrorKind, Input};
+use libapicheck::manifest::{find_manifest, Manifest};
+use libapicheck::{options, ErrorKind, Input};
 
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} FILE [options]", program);
+    let brief = format!(
+        "Usage: {} FILE|DIR|ARCHIVE [options]\n\nUse - as FILE to read from stdin.",
+        program
+    );
     print!("{}", opts.usage(&brief));
 }
 
@@ -18,9 +22,13 @@ fn main() -> Result<(), ErrorKind> {
     let args: Vec<String> = env::args().collect();
     // process options
     let mut opts = Options::new();
-    opts.optflagmulti("d", "debug", "display debug information");
-    opts.optflag("h", "help", "print this help menu");
-    opts.optopt("o", "output", "output file name", "FILE");
+    options::register_options(&mut opts);
+    opts.optopt(
+        "",
+        "stdin-filepath",
+        "path of the file read from stdin, used to resolve mod declarations",
+        "PATH",
+    );
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(f) => panic!("{}", f),
@@ -29,29 +37,45 @@ fn main() -> Result<(), ErrorKind> {
         print_usage(&args[0], opts);
         return Ok(());
     }
-    // setup config
-    let mut config = libapicheck::config::Config::default();
-    config.debug = matches.opt_count("d");
-    config.output = match matches.opt_str("o") {
-        Some(s) => {
-            if &s == "-" {
-                FileName::Stdout
-            } else {
-                FileName::Real(PathBuf::from(s.clone()))
-            }
-        }
-        None => FileName::Stdout,
-    };
     let input = if !matches.free.is_empty() {
         matches.free[0].clone()
     } else {
         print_usage(&args[0], opts);
         return Ok(());
     };
+    let stdin_filepath = matches.opt_str("stdin-filepath");
+    // for stdin, look for the manifest and the configuration file from the given path, or
+    // the current directory
+    let manifest_start = PathBuf::from(match (input.as_str(), &stdin_filepath) {
+        ("-", Some(path)) => path.as_str(),
+        ("-", None) => ".",
+        (input, _) => input,
+    });
+    // setup config
+    let mut config = options::config_from_matches(&matches, &manifest_start)?;
     // work !
     if config.debug > 0 {
         println!("Processing file {}", input);
     }
-    let input = Input::File(PathBuf::from(input));
+    let input = if input == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Input::Text(text, stdin_filepath.map(PathBuf::from))
+    } else {
+        Input::from_path(PathBuf::from(input))
+    };
+    // the feature sets of crate directories and archives are resolved against their own
+    // manifest
+    let is_source = matches!(input, Input::File(..) | Input::Text(..));
+    if is_source && (config.feature_matrix || config.feature_sets.is_some()) {
+        let manifest = match matches.opt_str("manifest-path") {
+            Some(path) => Some(Manifest::from_path(Path::new(&path))?),
+            None => match find_manifest(&manifest_start) {
+                Some(path) => Some(Manifest::from_path(&path)?),
+                None => None,
+            },
+        };
+        config.resolve_feature_sets(manifest.as_ref())?;
+    }
     libapicheck::process_file(input, &config)
 }
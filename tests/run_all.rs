@@ -12,16 +12,87 @@ fn apicheck_run_all() {
     utils::run_check_test("03").expect("test 03");
     utils::run_check_test("04").expect("test 04");
     utils::run_check_test("05").expect("test 05");
+    utils::run_check_test("aliases").expect("test aliases");
+    utils::run_check_test_args("aliases_2015", &["--edition", "2015"]).expect("test aliases_2015");
+    utils::run_check_test("aliases_imports").expect("test aliases_imports");
     utils::run_check_test("async").expect("test async");
+    utils::run_check_test_args("auto_traits", &["--auto-traits"]).expect("test auto_traits");
+    utils::run_check_test_args("canonical", &["--canonical"]).expect("test canonical");
+    utils::run_check_test_args("cfg", &["--cfg", "foo", "--cfg", "level=\"2\""]).expect("test cfg");
+    utils::run_check_test("cfg_if").expect("test cfg_if");
+    utils::run_check_test("cfg_if_mods").expect("test cfg_if_mods");
     utils::run_check_test("const_fn").expect("test const_fn");
+    utils::run_check_test_args("edition_2015", &["--edition", "2015"]).expect("test edition_2015");
     utils::run_check_test("enums_01").expect("test enums_01");
     utils::run_check_test("enums_02").expect("test enums_02");
+    utils::run_check_test("extern_crate").expect("test extern_crate");
+    let features_manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/features.toml");
+    let feature_set_args = [
+        "--manifest-path",
+        features_manifest,
+        "--feature-set",
+        "",
+        "--feature-set",
+        "alloc",
+    ];
+    utils::run_check_test_args("feature_set", &feature_set_args).expect("test feature_set");
+    let matrix_args = ["--feature-matrix", "--manifest-path", features_manifest];
+    utils::run_check_test_args("features_01", &matrix_args).expect("test features_01");
+    utils::run_check_test_args("features_02", &matrix_args).expect("test features_02");
+    utils::run_check_test_args("features_03", &matrix_args).expect("test features_03");
+    utils::run_check_test_args("ffi", &["--ffi"]).expect("test ffi");
+    utils::run_check_text_test("ffi_header", "h", &["--ffi-header"]).expect("test ffi_header");
+    let filter_args = ["--exclude=crate::experimental", "--include-kind=function"];
+    utils::run_check_test_args("filter", &filter_args).expect("test filter");
     utils::run_check_test("functions").expect("test functions");
+    let ignore_config = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/ignore.toml");
+    utils::run_check_test_args("ignore", &["--config-path", ignore_config]).expect("test ignore");
+    utils::run_check_test_args(
+        "ignore_modules",
+        &[
+            "--ignore",
+            "crate::ignore_*",
+            "--ignore",
+            "crate::**::internal",
+            "--ignore",
+            "!crate::public::internal",
+        ],
+    )
+    .expect("test ignore_modules");
+    utils::run_check_test("lifetimes").expect("test lifetimes");
+    utils::run_check_test("locations").expect("test locations");
+    utils::run_check_test("macro_calls_01").expect("test macro_calls_01");
+    utils::run_check_test("macro_calls_02").expect("test macro_calls_02");
+    utils::run_check_test_without_locations("macros", &["--expand-macros"]).expect("test macros");
+    utils::run_check_test_without_locations("macros_cfg_if", &["--expand-macros"])
+        .expect("test macros_cfg_if");
+    utils::run_check_test_without_locations("macros_scope", &["--expand-macros"])
+        .expect("test macros_scope");
     utils::run_check_test("mods").expect("test mods");
+    utils::run_check_test_stdin("mods").expect("test mods from stdin");
     utils::run_check_test("mods_02").expect("test mods_02");
+    utils::run_check_test_args("public_deps", &["--public-dependencies"])
+        .expect("test public_deps");
+    utils::run_check_test("opaque").expect("test opaque");
+    utils::run_check_test("repr").expect("test repr");
+    utils::run_check_test_args("resolve_paths", &["--resolve-paths"]).expect("test resolve_paths");
+    utils::run_check_test_args(
+        "resolve_globs",
+        &["--resolve-paths", "--public-dependencies"],
+    )
+    .expect("test resolve_globs");
     utils::run_check_test("structs_01").expect("test structs_01");
     utils::run_check_test("structs_02").expect("test structs_02");
     utils::run_check_test("traits").expect("test traits");
+    utils::run_check_test("unions").expect("test unions");
+    utils::run_check_test_args("unsafe_surface", &["--unsafe-surface"])
+        .expect("test unsafe_surface");
+    utils::run_check_input_test("crate_custom_lib", "packages/custom_lib", &[])
+        .expect("test crate directory");
+    utils::run_check_input_test("crate_custom_lib", "crates/custom_lib-0.1.0.crate", &[])
+        .expect("test crate archive");
+    utils::run_check_input_test_fail("packages/custom_lib", &["--edition", "2018"])
+        .expect("test crate directory with --edition");
 }
 
 #[test]
@@ -30,4 +101,89 @@ fn apidiff_run_all() {
     utils::run_diff_test("01", "02", 1).expect("diff 01/02");
     utils::run_diff_test("02", "03", 0).expect("diff 02/03");
     utils::run_diff_test("03", "04", 1).expect("diff 03/04");
+    utils::run_diff_test("aliases", "aliases_02", 0).expect("diff aliases/aliases_02");
+    utils::run_diff_test("aliases", "aliases_03", 1).expect("diff aliases/aliases_03");
+    utils::run_diff_test("aliases_2015", "aliases_2015_02", 0)
+        .expect("diff aliases_2015/aliases_2015_02");
+    utils::run_diff_test("aliases_2015", "aliases_2015_03", 0)
+        .expect("diff aliases_2015/aliases_2015_03");
+    utils::run_diff_test("aliases_2015", "aliases_2015_04", 1)
+        .expect("diff aliases_2015/aliases_2015_04");
+    utils::run_diff_test("aliases_imports", "aliases_imports_02", 0)
+        .expect("diff aliases_imports/aliases_imports_02");
+    utils::run_diff_test("aliases_imports", "aliases_imports_03", 1)
+        .expect("diff aliases_imports/aliases_imports_03");
+    utils::run_diff_test_args(
+        "aliases_imports",
+        "aliases_imports_03",
+        &["--crate-name", "my_crate"],
+        0,
+    )
+    .expect("diff aliases_imports/aliases_imports_03 with --crate-name");
+    utils::run_diff_test("auto_traits", "auto_traits", 0).expect("diff auto_traits");
+    utils::run_diff_test("auto_traits", "auto_traits_02", 1)
+        .expect("diff auto_traits/auto_traits_02");
+    utils::run_diff_test("cfg_if", "cfg_if", 0).expect("diff cfg_if/cfg_if");
+    utils::run_diff_test("cfg_if", "cfg_if_02", 1).expect("diff cfg_if/cfg_if_02");
+    utils::run_diff_test("features_01", "features_01", 0).expect("diff features_01/features_01");
+    utils::run_diff_test("features_01", "features_02", 1).expect("diff features_01/features_02");
+    utils::run_diff_test("features_01", "features_03", 1).expect("diff features_01/features_03");
+    utils::run_diff_test("lifetimes", "lifetimes_02", 0).expect("diff lifetimes/lifetimes_02");
+    utils::run_diff_test("lifetimes", "lifetimes_03", 1).expect("diff lifetimes/lifetimes_03");
+    utils::run_diff_test("macro_calls_01", "macro_calls_01", 0)
+        .expect("diff macro_calls_01/macro_calls_01");
+    utils::run_diff_test("macro_calls_01", "macro_calls_02", 1)
+        .expect("diff macro_calls_01/macro_calls_02");
+    utils::run_diff_test("mods", "mods_02", 1).expect("diff mods/mods_02");
+    utils::run_diff_test_args("mods", "mods_02", &["--exclude", "crate::bar"], 0)
+        .expect("diff mods/mods_02 --exclude");
+    utils::run_diff_test_args("mods", "mods_02", &["--include", "crate::a::**"], 0)
+        .expect("diff mods/mods_02 --include");
+    utils::run_diff_test_args("mods", "mods_02", &["--exclude-kind", "const"], 1)
+        .expect("diff mods/mods_02 --exclude-kind");
+    utils::run_diff_test("opaque", "opaque", 0).expect("diff opaque/opaque");
+    utils::run_diff_test("opaque", "opaque_02", 1).expect("diff opaque/opaque_02");
+    utils::run_diff_test("opaque", "opaque_03", 0).expect("diff opaque/opaque_03");
+    utils::run_diff_test("public_deps", "public_deps", 0).expect("diff public_deps");
+    utils::run_diff_test("public_deps", "public_deps_02", 1)
+        .expect("diff public_deps/public_deps_02");
+    utils::run_diff_test("repr", "repr", 0).expect("diff repr/repr");
+    utils::run_diff_test("repr", "repr_02", 1).expect("diff repr/repr_02");
+    utils::run_diff_test("resolve_paths", "resolve_paths_02", 0)
+        .expect("diff resolve_paths/resolve_paths_02");
+    utils::run_diff_test("unions", "unions", 0).expect("diff unions/unions");
+    utils::run_diff_test("unions", "unions_02", 1).expect("diff unions/unions_02");
+    utils::run_diff_test("unsafe_surface", "unsafe_surface", 0).expect("diff unsafe_surface");
+    utils::run_diff_test("unsafe_surface", "unsafe_surface_02", 1)
+        .expect("diff unsafe_surface/unsafe_surface_02");
+    utils::run_diff_test("unknown_kind", "unknown_kind", 2).expect("diff unknown_kind");
+    utils::run_diff_test("workspace_01", "workspace_01", 0)
+        .expect("diff workspace_01/workspace_01");
+    utils::run_diff_test("workspace_01", "workspace_02", 1)
+        .expect("diff workspace_01/workspace_02");
+}
+
+#[test]
+fn cargo_apicheck_run_all() {
+    utils::run_cargo_test("cargo_custom_lib", "custom_lib", &[]).expect("cargo custom_lib");
+    utils::run_cargo_test(
+        "cargo_custom_lib",
+        "custom_lib",
+        &["--package", "custom_lib"],
+    )
+    .expect("cargo custom_lib --package");
+    utils::run_cargo_test_fail("custom_lib", &["--package", "unknown"])
+        .expect("cargo custom_lib --package unknown");
+    utils::run_cargo_test("cargo_workspace", "workspace", &["--workspace"])
+        .expect("cargo workspace");
+    utils::run_cargo_test("cargo_workspace_beta", "workspace", &["--package", "beta"])
+        .expect("cargo workspace --package beta");
+    utils::run_cargo_test_fail("workspace", &[]).expect("cargo workspace without package");
+    utils::run_cargo_git_diff_test("custom_lib", "custom_lib", 0).expect("cargo --git-diff same");
+    utils::run_cargo_git_diff_test("custom_lib", "custom_lib_02", 1)
+        .expect("cargo --git-diff changed");
+    utils::run_cargo_baseline_test("custom_lib", false, 0).expect("cargo --baseline same");
+    utils::run_cargo_baseline_test("custom_lib_02", false, 1).expect("cargo --baseline changed");
+    utils::run_cargo_baseline_test("custom_lib_02", true, 1)
+        .expect("cargo --baseline changed, from archive");
 }
@@ -2,27 +2,260 @@ mod error;
 mod workdir;
 
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub fn run_check_test(name: &str) -> error::Result<()> {
-    println!("Running test apicheck [{}]", name);
+    run_check_test_args(name, &[])
+}
+
+pub fn run_check_test_args(name: &str, args: &[&str]) -> error::Result<()> {
+    run_check(name, args, true)
+}
+
+/// Run a test whose expected results do not contain the source locations, for items
+/// expanded from macros: their locations are the ones of the tokens in the macro definitions.
+pub fn run_check_test_without_locations(name: &str, args: &[&str]) -> error::Result<()> {
+    run_check(name, args, false)
+}
+
+fn run_check(name: &str, args: &[&str], check_locations: bool) -> error::Result<()> {
+    run_check_input(name, &format!("{}.rs", name), args, check_locations)
+}
+
+/// Run a test on `assets/<input>`, such as a crate directory or a packaged crate.
+pub fn run_check_input_test(name: &str, input: &str, args: &[&str]) -> error::Result<()> {
+    run_check_input(name, input, args, true)
+}
+
+/// Run apicheck on `assets/<input>`, and check that it fails.
+pub fn run_check_input_test_fail(input: &str, args: &[&str]) -> error::Result<()> {
+    println!(
+        "Running test apicheck [{}] {:?}, expecting failure",
+        input, args
+    );
+    let out_dir = env::var("CARGO_MANIFEST_DIR")?;
+    let test_source = Path::new(&out_dir).join("assets").join(input);
+
+    let wrk = workdir::Workdir::new("apicheck");
+    let mut cmd = wrk.check(test_source.to_str().unwrap());
+    cmd.args(args);
+
+    let o = wrk.run(&mut cmd)?;
+    assert!(!o.status.success(), "apicheck succeeded: {:?}", cmd);
+
+    Ok(())
+}
+
+fn run_check_input(
+    name: &str,
+    input: &str,
+    args: &[&str],
+    check_locations: bool,
+) -> error::Result<()> {
+    println!("Running test apicheck [{}] {} {:?}", name, input, args);
     let out_dir = env::var("CARGO_MANIFEST_DIR")?;
     // dbg!(&out_dir);
 
-    let test_source = Path::new(&out_dir)
-        .join("assets")
-        .join(format!("{}.rs", name));
+    let test_source = Path::new(&out_dir).join("assets").join(input);
 
     // test_index("slice_index", 1, "b", true, false);
     let wrk = workdir::Workdir::new("apicheck");
     let mut cmd = wrk.check(test_source.to_str().unwrap());
+    cmd.args(args);
     // cmd.arg("-o blah.json");
 
     let got: String = wrk.stdout(&mut cmd);
     // eprintln!("got: {}", got);
 
+    compare_check_result(name, &got, check_locations)
+}
+
+/// Run a test whose expected result is the text in `assets/<name>.<extension>`, like a C
+/// header printed with `--ffi-header`.
+pub fn run_check_text_test(name: &str, extension: &str, args: &[&str]) -> error::Result<()> {
+    println!("Running test apicheck [{}] {:?}", name, args);
+    let out_dir = env::var("CARGO_MANIFEST_DIR")?;
+    let assets = Path::new(&out_dir).join("assets");
+    let test_source = assets.join(format!("{}.rs", name));
+
+    let wrk = workdir::Workdir::new("apicheck");
+    let mut cmd = wrk.check(test_source.to_str().unwrap());
+    cmd.args(args);
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = fs::read_to_string(assets.join(format!("{}.{}", name, extension)))?;
+    assert_eq!(expected.trim_end(), got.trim_end());
+
+    Ok(())
+}
+
+pub fn run_check_test_stdin(name: &str) -> error::Result<()> {
+    println!("Running test apicheck [{}] from stdin", name);
+    let out_dir = env::var("CARGO_MANIFEST_DIR")?;
+
+    let test_source = Path::new(&out_dir)
+        .join("assets")
+        .join(format!("{}.rs", name));
+
+    let wrk = workdir::Workdir::new("apicheck");
+    let mut cmd = wrk.check("-");
+    cmd.arg("--stdin-filepath").arg(&test_source);
+    cmd.stdin(File::open(&test_source)?);
+
+    let got: String = wrk.stdout(&mut cmd);
+
+    compare_check_result(name, &got, true)
+}
+
+/// Run `cargo apicheck` on the package in `assets/packages/<package>`, and compare the result
+/// with `assets/<name>.json`.
+pub fn run_cargo_test(name: &str, package: &str, args: &[&str]) -> error::Result<()> {
+    println!("Running test cargo apicheck [{}] {:?}", name, args);
+    let wrk = workdir::Workdir::new("cargo-apicheck");
+    let mut cmd = wrk.cargo();
+    cmd.arg("--manifest-path").arg(package_manifest(package)?);
+    cmd.args(args);
+
+    let got: String = wrk.stdout(&mut cmd);
+
+    compare_check_result(name, &got, true)
+}
+
+/// Run `cargo apicheck` on the package in `assets/packages/<package>`, and check that it fails.
+pub fn run_cargo_test_fail(package: &str, args: &[&str]) -> error::Result<()> {
+    println!(
+        "Running test cargo apicheck [{}] {:?}, expecting failure",
+        package, args
+    );
+    let wrk = workdir::Workdir::new("cargo-apicheck");
+    let mut cmd = wrk.cargo();
+    cmd.arg("--manifest-path").arg(package_manifest(package)?);
+    cmd.args(args);
+
+    let o = wrk.run(&mut cmd)?;
+    assert!(!o.status.success(), "cargo apicheck succeeded: {:?}", cmd);
+
+    Ok(())
+}
+
+/// Commit the package in `assets/packages/<old>` to a new git repository, replace its files
+/// with those of `assets/packages/<new>` in a second commit, and compare both revisions with
+/// `cargo apicheck --git-diff`.
+pub fn run_cargo_git_diff_test(old: &str, new: &str, expected_rc: i32) -> error::Result<()> {
+    println!(
+        "Running test cargo apicheck --git-diff [{}] [{}], expecting rc {}",
+        old, new, expected_rc
+    );
+    let wrk = workdir::Workdir::new("cargo-apicheck");
+    let repo = wrk.path("repo");
+    copy_package(old, &repo)?;
+    git(&wrk, &["init", "--quiet", "repo"]);
+    git_commit(&wrk, "repo", "old");
+    copy_package(new, &repo)?;
+    git_commit(&wrk, "repo", "new");
+
+    let mut cmd = wrk.cargo();
+    cmd.arg("--manifest-path")
+        .arg(repo.join("Cargo.toml"))
+        .args(&["--git-diff", "HEAD~1", "HEAD"]);
+
+    let o = wrk.run(&mut cmd)?;
+    assert_eq!(o.status.code(), Some(expected_rc), "{:?}", o);
+
+    Ok(())
+}
+
+/// Compare the package in `assets/packages/<package>` with `custom_lib@0.1.0` from a local
+/// registry, which holds either the unpacked sources of `assets/packages/custom_lib` or the
+/// archive `assets/crates/custom_lib-0.1.0.crate`.
+pub fn run_cargo_baseline_test(
+    package: &str,
+    from_archive: bool,
+    expected_rc: i32,
+) -> error::Result<()> {
+    println!(
+        "Running test cargo apicheck --baseline [{}] (archive: {}), expecting rc {}",
+        package, from_archive, expected_rc
+    );
+    let wrk = workdir::Workdir::new("cargo-apicheck");
+    let registry = wrk.path("cargo-home").join("registry");
+    if from_archive {
+        let index = registry.join("cache").join("index");
+        fs::create_dir_all(&index)?;
+        fs::copy(
+            crate_archive("custom_lib-0.1.0")?,
+            index.join("custom_lib-0.1.0.crate"),
+        )?;
+    } else {
+        let dir = registry.join("src").join("index").join("custom_lib-0.1.0");
+        copy_package("custom_lib", &dir)?;
+    }
+
+    let mut cmd = wrk.cargo();
+    cmd.env("CARGO_HOME", wrk.path("cargo-home"))
+        .arg("--manifest-path")
+        .arg(package_manifest(package)?)
+        .args(&["--baseline", "custom_lib@0.1.0"]);
+
+    let o = wrk.run(&mut cmd)?;
+    assert_eq!(o.status.code(), Some(expected_rc), "{:?}", o);
+
+    Ok(())
+}
+
+fn git(wrk: &workdir::Workdir, args: &[&str]) {
+    let mut cmd = wrk.git();
+    cmd.args(args);
+    wrk.output(&mut cmd);
+}
+
+/// Commit every file of the git repository `repo`, even if nothing changed.
+fn git_commit(wrk: &workdir::Workdir, repo: &str, message: &str) {
+    git(wrk, &["-C", repo, "add", "--all"]);
+    git(wrk, &["-C", repo, "commit", "--allow-empty", "-m", message]);
+}
+
+/// Copy the files of `assets/packages/<package>` into `dest`.
+fn copy_package(package: &str, dest: &Path) -> error::Result<()> {
+    let manifest = package_manifest(package)?;
+    copy_dir(manifest.parent().expect("package directory"), dest)
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> error::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &path)?;
+        } else {
+            fs::copy(entry.path(), path)?;
+        }
+    }
+    Ok(())
+}
+
+fn crate_archive(name: &str) -> error::Result<PathBuf> {
+    let out_dir = env::var("CARGO_MANIFEST_DIR")?;
+    Ok(Path::new(&out_dir)
+        .join("assets")
+        .join("crates")
+        .join(format!("{}.crate", name)))
+}
+
+fn package_manifest(package: &str) -> error::Result<PathBuf> {
+    let out_dir = env::var("CARGO_MANIFEST_DIR")?;
+    Ok(Path::new(&out_dir)
+        .join("assets")
+        .join("packages")
+        .join(package)
+        .join("Cargo.toml"))
+}
+
+fn compare_check_result(name: &str, got: &str, check_locations: bool) -> error::Result<()> {
+    let out_dir = env::var("CARGO_MANIFEST_DIR")?;
     let path_txt = Path::new(&out_dir)
         .join("assets")
         .join(format!("{}.json", name));
@@ -35,8 +268,11 @@ pub fn run_check_test(name: &str) -> error::Result<()> {
     js_clear_path(&mut js);
     // println!("{:?}", js);
 
-    let mut js2 = json::parse(&got)?;
+    let mut js2 = json::parse(got)?;
     js_clear_path(&mut js2);
+    if !check_locations {
+        js_clear_locations(&mut js2);
+    }
     // println!("{:?}", js2);
     assert_eq!(js, js2);
 
@@ -44,7 +280,16 @@ pub fn run_check_test(name: &str) -> error::Result<()> {
 }
 
 pub fn run_diff_test(name1: &str, name2: &str, expected_rc: i32) -> error::Result<()> {
-    println!("Running test apidiff [{} / {}]", name1, name2);
+    run_diff_test_args(name1, name2, &[], expected_rc)
+}
+
+pub fn run_diff_test_args(
+    name1: &str,
+    name2: &str,
+    args: &[&str],
+    expected_rc: i32,
+) -> error::Result<()> {
+    println!("Running test apidiff [{} / {}] {:?}", name1, name2, args);
     let out_dir = env::var("CARGO_MANIFEST_DIR")?;
     // dbg!(&out_dir);
 
@@ -63,6 +308,7 @@ pub fn run_diff_test(name1: &str, name2: &str, expected_rc: i32) -> error::Resul
     );
     cmd.arg("-p 10"); // do not compare file paths
                       // cmd.arg("-o blah.json");
+    cmd.args(args);
 
     let o = wrk.run(&mut cmd).expect("could not run diff tool");
     // dbg!(&o);
@@ -75,7 +321,43 @@ pub fn run_diff_test(name1: &str, name2: &str, expected_rc: i32) -> error::Resul
 }
 
 fn js_clear_path(js: &mut json::JsonValue) {
+    // workspaces have a description per crate
+    if js.has_key("crates") {
+        for (_, krate) in js["crates"].entries_mut() {
+            js_clear_path(krate);
+        }
+    }
     for entry in js["modules"].members_mut() {
         entry.remove("path");
+        js_clear_location_files(&mut entry["items"]);
+    }
+}
+
+/// Remove the file names from source locations, they depend on the working directory.
+fn js_clear_location_files(js: &mut json::JsonValue) {
+    if js.is_object() {
+        if js.has_key("location") {
+            js["location"].remove("file");
+        }
+        for (_, value) in js.entries_mut() {
+            js_clear_location_files(value);
+        }
+    } else {
+        for member in js.members_mut() {
+            js_clear_location_files(member);
+        }
+    }
+}
+
+fn js_clear_locations(js: &mut json::JsonValue) {
+    if js.is_object() {
+        js.remove("location");
+        for (_, value) in js.entries_mut() {
+            js_clear_locations(value);
+        }
+    } else {
+        for member in js.members_mut() {
+            js_clear_locations(member);
+        }
     }
 }
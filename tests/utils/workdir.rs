@@ -100,6 +100,22 @@ impl Workdir {
         cmd
     }
 
+    pub fn cargo(&self) -> process::Command {
+        let mut cmd = process::Command::new(&self.cargo_apicheck_bin());
+        cmd.current_dir(&self.dir);
+        cmd
+    }
+
+    pub fn git(&self) -> process::Command {
+        let mut cmd = process::Command::new("git");
+        cmd.current_dir(&self.dir)
+            .env("GIT_AUTHOR_NAME", "apicheck")
+            .env("GIT_AUTHOR_EMAIL", "apicheck@example.com")
+            .env("GIT_COMMITTER_NAME", "apicheck")
+            .env("GIT_COMMITTER_EMAIL", "apicheck@example.com");
+        cmd
+    }
+
     pub fn output(&self, cmd: &mut process::Command) -> process::Output {
         let o = cmd.output().unwrap();
         if !o.status.success() {
@@ -162,9 +178,9 @@ impl Workdir {
     //     o.parse().ok().expect("fromstr")
     // }
 
-    // pub fn path(&self, name: &str) -> PathBuf {
-    //     self.dir.join(name)
-    // }
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
 
     pub fn apicheck_bin(&self) -> PathBuf {
         self.root.join("apicheck")
@@ -173,6 +189,10 @@ impl Workdir {
     pub fn apidiff_bin(&self) -> PathBuf {
         self.root.join("apidiff")
     }
+
+    pub fn cargo_apicheck_bin(&self) -> PathBuf {
+        self.root.join("cargo-apicheck")
+    }
 }
 
 impl fmt::Debug for Workdir {